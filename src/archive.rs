@@ -0,0 +1,383 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::MAX_ARCHIVE_SCAN_SIZE;
+use crate::hash::StableHasher;
+use crate::types::{EntryKind, FileEntry};
+
+/// 压缩包内的单个成员
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveMember {
+    /// 包内相对路径，统一使用 `\` 分隔
+    pub path: String,
+    pub size: u64,
+    /// 压缩后大小（tar 没有逐成员的压缩信息，为 `None`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed_size: Option<u64>,
+    pub modified: u64,
+    pub is_dir: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    SevenZ,
+}
+
+// rar 没有纯 Rust 的解包实现，暂不支持
+fn detect_format(path: &Path) -> Option<ArchiveFormat> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveFormat::TarGz)
+    } else if name.ends_with(".zip") {
+        Some(ArchiveFormat::Zip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveFormat::Tar)
+    } else if name.ends_with(".7z") {
+        Some(ArchiveFormat::SevenZ)
+    } else {
+        None
+    }
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    detect_format(path).is_some()
+}
+
+/// 列出压缩包全部成员
+pub fn list_members(archive: &Path) -> Result<Vec<ArchiveMember>> {
+    let format = detect_format(archive).with_context(|| format!("不支持的压缩格式: {:?}", archive))?;
+
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+            let mut members = Vec::with_capacity(zip.len());
+            for i in 0..zip.len() {
+                let file = zip.by_index_raw(i)?;
+                members.push(ArchiveMember {
+                    path: normalize_member_path(&decode_name(file.name_raw())),
+                    size: file.size(),
+                    compressed_size: Some(file.compressed_size()),
+                    modified: file.last_modified().and_then(zip_time_to_unix).unwrap_or(0),
+                    is_dir: file.is_dir(),
+                });
+            }
+            Ok(members)
+        }
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+            // tar 只能顺序读取，过大的包整体解压代价太高
+            let len = std::fs::metadata(archive)?.len();
+            if len > MAX_ARCHIVE_SCAN_SIZE {
+                anyhow::bail!("压缩包过大 ({} 字节)，跳过", len);
+            }
+            let mut tar = tar::Archive::new(open_tar_stream(archive, format)?);
+            let mut members = Vec::new();
+            for entry in tar.entries()? {
+                let entry = entry?;
+                let header = entry.header();
+                members.push(ArchiveMember {
+                    path: normalize_member_path(&decode_name(&entry.path_bytes())),
+                    size: header.size().unwrap_or(0),
+                    compressed_size: None,
+                    modified: header.mtime().unwrap_or(0),
+                    is_dir: header.entry_type().is_dir(),
+                });
+            }
+            Ok(members)
+        }
+        ArchiveFormat::SevenZ => {
+            let sz = sevenz_rust::Archive::open(archive).map_err(|e| anyhow::anyhow!("7z 解析失败: {}", e))?;
+            Ok(sz
+                .files
+                .iter()
+                .map(|f| ArchiveMember {
+                    path: normalize_member_path(f.name()),
+                    size: f.size(),
+                    compressed_size: Some(f.compressed_size),
+                    modified: if f.has_last_modified_date {
+                        filetime_to_unix(u64::from(f.last_modified_date()))
+                    } else {
+                        0
+                    },
+                    is_dir: f.is_directory(),
+                })
+                .collect())
+        }
+    }
+}
+
+/// 把压缩包成员转换为虚拟条目，路径形如 `D:\backup.zip\docs\plan.docx`
+pub fn member_entries(archive_path: &str, drive: char) -> Vec<FileEntry> {
+    let members = match list_members(Path::new(archive_path)) {
+        Ok(m) => m,
+        Err(e) => {
+            tracing::debug!("读取压缩包 {} 失败: {}", archive_path, e);
+            return Vec::new();
+        }
+    };
+
+    members
+        .into_iter()
+        .filter(|m| !m.path.is_empty())
+        .map(|m| {
+            let name = m.path.rsplit('\\').next().unwrap_or(&m.path).to_string();
+            FileEntry {
+                extension: if m.is_dir { String::new() } else { Path::new(&name).extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase() },
+                name,
+                path: format!("{}\\{}", archive_path, m.path),
+                size: m.size,
+                modified: m.modified,
+                is_dir: m.is_dir,
+                drive,
+                kind: EntryKind::ArchiveMember,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// 拆分虚拟路径为 (压缩包路径, 包内成员路径)；普通路径返回 `None`
+pub fn split_virtual_path(path: &Path) -> Option<(PathBuf, String)> {
+    let s = path.to_string_lossy();
+    for (i, c) in s.char_indices() {
+        if c != '\\' && c != '/' {
+            continue;
+        }
+        let archive = Path::new(&s[..i]);
+        if is_archive(archive) && archive.is_file() {
+            let member = normalize_member_path(&s[i + 1..]);
+            if !member.is_empty() {
+                return Some((archive.to_path_buf(), member));
+            }
+        }
+    }
+    None
+}
+
+/// 解压单个成员到临时目录，返回解压后的文件路径
+pub fn extract_member(archive: &Path, member: &str) -> Result<PathBuf> {
+    let member = normalize_member_path(member);
+    if !member.split('\\').all(is_plain_component) {
+        anyhow::bail!("非法的成员路径: {}", member);
+    }
+
+    // 按完整路径区分同名压缩包，目录名里保留文件名便于辨认
+    let name = archive.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let key = StableHasher::new().write_str(&archive.to_string_lossy()).finish();
+    let target = member
+        .split('\\')
+        .fold(std::env::temp_dir().join("StarSearch").join(format!("{}-{:016x}", name, key)), |p, c| p.join(c));
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let format = detect_format(archive).with_context(|| format!("不支持的压缩格式: {:?}", archive))?;
+    let found = match format {
+        ArchiveFormat::Zip => {
+            let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+            let mut found = false;
+            for i in 0..zip.len() {
+                let mut file = zip.by_index(i)?;
+                if normalize_member_path(&decode_name(file.name_raw())) == member {
+                    std::io::copy(&mut file, &mut File::create(&target)?)?;
+                    found = true;
+                    break;
+                }
+            }
+            found
+        }
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+            let mut tar = tar::Archive::new(open_tar_stream(archive, format)?);
+            let mut found = false;
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                if normalize_member_path(&decode_name(&entry.path_bytes())) == member {
+                    std::io::copy(&mut entry, &mut File::create(&target)?)?;
+                    found = true;
+                    break;
+                }
+            }
+            found
+        }
+        ArchiveFormat::SevenZ => {
+            let mut reader = sevenz_rust::SevenZReader::open(archive, sevenz_rust::Password::empty())
+                .map_err(|e| anyhow::anyhow!("7z 解析失败: {}", e))?;
+            let mut found = false;
+            reader
+                .for_each_entries(|entry, data| {
+                    if normalize_member_path(entry.name()) != member {
+                        return Ok(true);
+                    }
+                    std::io::copy(data, &mut File::create(&target)?)?;
+                    found = true;
+                    Ok(false)
+                })
+                .map_err(|e| anyhow::anyhow!("7z 解压失败: {}", e))?;
+            found
+        }
+    };
+
+    if !found {
+        anyhow::bail!("压缩包 {:?} 中没有成员 {}", archive, member);
+    }
+    Ok(target)
+}
+
+/// 打开文件；压缩包内的虚拟路径先解压到临时目录再打开
+pub fn open_path(path: &Path) -> Result<()> {
+    if !path.exists() {
+        if let Some((archive, member)) = split_virtual_path(path) {
+            let extracted = extract_member(&archive, &member)?;
+            open::that(extracted)?;
+            return Ok(());
+        }
+    }
+    open::that(path)?;
+    Ok(())
+}
+
+fn open_tar_stream(archive: &Path, format: ArchiveFormat) -> Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(archive)?);
+    Ok(if format == ArchiveFormat::TarGz {
+        Box::new(flate2::read::GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    })
+}
+
+/// 成员名编码：优先 UTF-8，否则按 GBK 解码（中文 Windows 打包的 zip 常见）
+fn decode_name(raw: &[u8]) -> String {
+    match std::str::from_utf8(raw) {
+        Ok(s) => s.to_string(),
+        Err(_) => encoding_rs::GBK.decode(raw).0.into_owned(),
+    }
+}
+
+/// 统一分隔符并去掉 tar 常见的 `./` 前缀
+fn normalize_member_path(name: &str) -> String {
+    name.split(['/', '\\'])
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect::<Vec<_>>()
+        .join("\\")
+}
+
+/// 成员路径的一段只能是普通文件名：`..`、盘符（`C:`）、根目录与 `:` 数据流拼接后都会跳出临时目录
+fn is_plain_component(part: &str) -> bool {
+    let mut components = Path::new(part).components();
+    matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) && !part.contains(':')
+}
+
+/// zip 内的时间没有时区，按本地时间解释
+fn zip_time_to_unix(t: zip::DateTime) -> Option<u64> {
+    let dt = chrono::NaiveDate::from_ymd_opt(t.year() as i32, t.month() as u32, t.day() as u32)?
+        .and_hms_opt(t.hour() as u32, t.minute() as u32, t.second() as u32)?;
+    let ts = dt.and_local_timezone(chrono::Local).earliest()?.timestamp();
+    u64::try_from(ts).ok()
+}
+
+/// Windows FILETIME（1601 年起的 100ns 计数）转 Unix 秒
+fn filetime_to_unix(ft: u64) -> u64 {
+    (ft / 10_000_000).saturating_sub(11_644_473_600)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("starsearch-archive-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &str)]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_tar_gz(path: &Path, files: &[(&str, &str)]) {
+        let gz = flate2::write::GzEncoder::new(File::create(path).unwrap(), flate2::Compression::default());
+        let mut tar = tar::Builder::new(gz);
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(1_700_000_000);
+            header.set_cksum();
+            tar.append_data(&mut header, name, content.as_bytes()).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn lists_zip_and_tar_members() {
+        let dir = temp_dir("list");
+        let files = [("docs/plan.txt", "plan"), ("readme.md", "hello world")];
+        write_zip(&dir.join("a.zip"), &files);
+        write_tar_gz(&dir.join("a.tar.gz"), &files);
+
+        for name in ["a.zip", "a.tar.gz"] {
+            let members = list_members(&dir.join(name)).unwrap();
+            let paths: Vec<_> = members.iter().map(|m| (m.path.as_str(), m.size)).collect();
+            assert_eq!(paths, [("docs\\plan.txt", 4), ("readme.md", 11)], "{}", name);
+        }
+        assert_eq!(list_members(&dir.join("a.tar.gz")).unwrap()[0].modified, 1_700_000_000);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn extracts_members_per_archive_path() {
+        let dir = temp_dir("extract");
+        std::fs::create_dir_all(dir.join("x")).unwrap();
+        std::fs::create_dir_all(dir.join("y")).unwrap();
+        write_zip(&dir.join("x").join("same.zip"), &[("a.txt", "from x")]);
+        write_zip(&dir.join("y").join("same.zip"), &[("a.txt", "from y")]);
+
+        let x = extract_member(&dir.join("x").join("same.zip"), "a.txt").unwrap();
+        let y = extract_member(&dir.join("y").join("same.zip"), "a.txt").unwrap();
+        assert_ne!(x, y);
+        assert_eq!(std::fs::read_to_string(&x).unwrap(), "from x");
+        assert_eq!(std::fs::read_to_string(&y).unwrap(), "from y");
+        assert!(extract_member(&dir.join("x").join("same.zip"), "missing.txt").is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn rejects_escaping_member_paths() {
+        for member in ["..\\evil.txt", "a/../../evil", "C:\\evil.txt", "C:evil.txt", "a\\b:stream", ""] {
+            let err = extract_member(Path::new("/nonexistent/a.zip"), member).unwrap_err();
+            assert!(err.to_string().contains("非法的成员路径"), "{}: {}", member, err);
+        }
+        assert!(is_plain_component("plan.docx"));
+        assert!(!is_plain_component("/"));
+    }
+
+    #[test]
+    fn splits_virtual_paths() {
+        let dir = temp_dir("split");
+        let archive = dir.join("b.zip");
+        write_zip(&archive, &[("docs/plan.txt", "x")]);
+        let virtual_path = archive.join("docs").join("plan.txt");
+        assert_eq!(split_virtual_path(&virtual_path), Some((archive.clone(), "docs\\plan.txt".to_string())));
+        assert_eq!(split_virtual_path(&dir.join("plain.txt")), None);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn decodes_gbk_names() {
+        let (gbk, _, _) = encoding_rs::GBK.encode("报告.txt");
+        assert_eq!(decode_name(&gbk), "报告.txt");
+        assert_eq!(normalize_member_path("./a//b/c.txt"), "a\\b\\c.txt");
+    }
+}
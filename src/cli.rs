@@ -1,15 +1,86 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
 use serde_json::json;
 
 use crate::config::RuntimeConfig;
-use crate::content_search::ContentSearcher;
-use crate::ntfs_search::LocalNtfsSearcher;
+use crate::content_search::{CancelToken, CaseMode, ContentSearcher};
+use crate::ntfs_search::{LocalNtfsSearcher, NtfsScanOptions};
+use crate::query::LocalQuery;
+use crate::replace::Replacer;
+use crate::sort::{self, entry_value, match_value, path_value};
+use crate::types::SortKey;
+
+/// 未指定 `-m` 时的结果数
+const DEFAULT_MAX_RESULTS: usize = 10;
+
+/// `starsearch` 命令行：无界面，供脚本与 AI 调用
+#[derive(Parser, Debug)]
+#[command(name = "starsearch", author, version, about = "StarSearch 极速搜索工具（AI调用专用）", long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 文件名搜索（支持 `content:` 组合查询、替换、预览等全部选项）
+    Search(CliArgs),
+    /// 内容搜索（等同 `search --content`）
+    Content(CliArgs),
+    /// 本地文件名索引
+    Index {
+        #[command(subcommand)]
+        action: IndexAction,
+    },
+    /// 后台检索服务（Windows 为系统服务 + 命名管道，其他平台为前台进程 + Unix 套接字）
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+    /// 显示配置与数据文件位置
+    Config,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum IndexAction {
+    /// 忽略缓存重新扫描，完成后覆盖缓存
+    Build {
+        /// 遍历全部 MFT 记录恢复已删除文件
+        #[arg(long = "deleted")]
+        deleted: bool,
+        /// 扫描 NTFS 备用数据流
+        #[arg(long = "streams")]
+        streams: bool,
+        /// 把压缩包成员作为虚拟路径加入索引
+        #[arg(long = "archives")]
+        archives: bool,
+        /// 同时增量更新全文索引（范围为 `local_work_dirs`）
+        #[arg(long = "fulltext")]
+        fulltext: bool,
+    },
+    /// 索引缓存、全文索引与服务状态
+    Status,
+    /// 导出索引中的全部条目（没有缓存时先扫描）
+    Export {
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ServiceAction {
+    /// 安装并启动系统服务（仅 Windows，需要管理员权限）
+    Install,
+    /// 停止并删除系统服务（仅 Windows）
+    Uninstall,
+    /// 运行服务（由服务管理器调用，也可在前台运行）
+    Run,
+}
 
 #[derive(Parser, Debug)]
-#[command(author, version, about = "StarSearch 极速搜索工具（AI调用专用）", long_about = None)]
 pub struct CliArgs {
     /// 搜索关键词
-    #[arg(short = 'q', long = "query", required = true)]
+    #[arg(short = 'q', long = "query", required_unless_present = "preview", default_value = "")]
     pub query: String,
 
     /// 自定义搜索路径（U盘/外挂盘，默认=本机）
@@ -20,77 +91,1159 @@ pub struct CliArgs {
     #[arg(short = 'c', long = "content")]
     pub content: bool,
 
-    /// 最大结果数（默认=10）
-    #[arg(short = 'm', long = "max-results", default_value_t = 10)]
-    pub max_results: usize,
+    /// 最大结果数（默认=10；配合 `--exec`/`--exec-batch` 时默认不限，与 fd 一致对全部结果执行）
+    #[arg(short = 'm', long = "max-results")]
+    pub max_results: Option<usize>,
+
+    /// NTFS 原始镜像文件（dd/.img，可离线在 Linux 上检索）
+    #[arg(long = "image")]
+    pub image: Option<std::path::PathBuf>,
+
+    /// 分区在镜像内的字节偏移（默认自动探测 MBR/GPT）
+    #[arg(long = "image-offset", requires = "image")]
+    pub image_offset: Option<u64>,
+
+    /// 遍历全部 MFT 记录恢复已删除文件（配合 `deleted:` 检索）
+    #[arg(long = "deleted")]
+    pub deleted: bool,
+
+    /// 扫描 NTFS 备用数据流（配合 `ads:` 检索）
+    #[arg(long = "streams")]
+    pub streams: bool,
+
+    /// 把 zip/tar/7z 压缩包成员作为虚拟路径一并搜索
+    #[arg(long = "archives")]
+    pub archives: bool,
+
+    /// 内容搜索：匹配行前后各显示 N 行上下文
+    #[arg(short = 'C', long = "context", default_value_t = 0)]
+    pub context: usize,
+
+    /// 内容搜索：匹配行之前的上下文行数（覆盖 --context）
+    #[arg(short = 'B', long = "before")]
+    pub before: Option<usize>,
+
+    /// 内容搜索：匹配行之后的上下文行数（覆盖 --context）
+    #[arg(short = 'A', long = "after")]
+    pub after: Option<usize>,
+
+    /// 内容搜索：同一文件的匹配合并为一条记录
+    #[arg(long = "group")]
+    pub group: bool,
+
+    /// 内容搜索：只搜索这些扩展名（逗号分隔，如 rs,toml）
+    #[arg(long = "ext", value_delimiter = ',')]
+    pub ext: Vec<String>,
+
+    /// 内容搜索：排除这些扩展名（逗号分隔）
+    #[arg(long = "exclude-ext", value_delimiter = ',')]
+    pub exclude_ext: Vec<String>,
+
+    /// 内容搜索：字面量查询先查全文索引，只对候选文件 grep 验证；
+    /// 上次更新后改动的文件查不到，需配合 `--fulltext-update`，索引未建立时退回全量搜索
+    #[arg(long = "fulltext")]
+    pub fulltext: bool,
+
+    /// 内容搜索前按修改时间增量更新全文索引（首次即全量建立）
+    #[arg(long = "fulltext-update")]
+    pub fulltext_update: bool,
+
+    /// 内容搜索：单文件最多返回的匹配数
+    #[arg(long = "max-per-file", default_value_t = crate::config::MAX_GREP_PER_FILE)]
+    pub max_per_file: usize,
+
+    /// 内容搜索：找到即逐行输出 JSON（JSON Lines），最后一行为汇总
+    #[arg(long = "stream")]
+    pub stream: bool,
+
+    /// 内容搜索：时间预算（毫秒），超时返回已找到的结果
+    #[arg(long = "timeout")]
+    pub timeout: Option<u64>,
+
+    /// 内容搜索：按普通字符串匹配（不解析正则）
+    #[arg(short = 'F', long = "fixed-strings")]
+    pub fixed_strings: bool,
+
+    /// 内容搜索：忽略大小写
+    #[arg(short = 'i', long = "ignore-case", conflicts_with = "smart_case")]
+    pub ignore_case: bool,
+
+    /// 内容搜索：查询全小写时忽略大小写，否则区分
+    #[arg(short = 'S', long = "smart-case")]
+    pub smart_case: bool,
+
+    /// 内容搜索：只匹配完整单词
+    #[arg(short = 'w', long = "word-regexp")]
+    pub word: bool,
+
+    /// 内容搜索：允许跨行匹配
+    #[arg(short = 'U', long = "multiline")]
+    pub multiline: bool,
+
+    /// 内容搜索：结果附带匹配度明细（BM25、文件名/路径、时间加成）
+    #[arg(long = "debug-score")]
+    pub debug_score: bool,
+
+    /// 内容搜索：把匹配替换为该文本（支持 `$1`、`${name}` 捕获组），默认只输出 diff
+    #[arg(long = "replace")]
+    pub replace: Option<String>,
+
+    /// 与 --replace 配合：实际写回文件（原文件备份到数据目录）
+    #[arg(long = "apply", requires = "replace")]
+    pub apply: bool,
+
+    /// 高亮预览指定文件（不搜索）
+    #[arg(long = "preview")]
+    pub preview: Option<std::path::PathBuf>,
+
+    /// 预览输出格式
+    #[arg(long = "preview-format", value_enum, default_value_t = PreviewFormat::Ansi)]
+    pub preview_format: PreviewFormat,
+
+    /// 结果排序字段（大小、修改时间、匹配度默认从大到小）
+    #[arg(long = "sort", value_enum, default_value_t = SortKey::Score, conflicts_with = "stream")]
+    pub sort: SortKey,
+
+    /// 反转排序方向
+    #[arg(long = "reverse")]
+    pub reverse: bool,
+
+    /// 对每个结果执行命令，如 `--exec 'code {}'`；占位符 {} 路径、{/} 文件名、{//} 父目录、
+    /// {.} 去扩展名路径、{/.} 去扩展名文件名、{ext} 扩展名，无占位符时追加在末尾
+    #[arg(short = 'x', long = "exec", conflicts_with_all = ["exec_batch", "stream", "preview", "replace"])]
+    pub exec: Option<String>,
+
+    /// 所有结果合成一条命令执行，如 `--exec-batch 'zip out.zip {}'`
+    #[arg(short = 'X', long = "exec-batch", conflicts_with_all = ["stream", "preview", "replace"])]
+    pub exec_batch: Option<String>,
+
+    /// `--exec` 的并行数（默认=CPU 核数）
+    #[arg(short = 'j', long = "jobs")]
+    pub jobs: Option<usize>,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
 }
 
-// CLI入口
-pub async fn run_cli(args: CliArgs) -> anyhow::Result<()> {
+/// 结果输出选项，搜索与索引导出共用
+#[derive(Args, Debug, Clone, Default)]
+pub struct OutputArgs {
+    /// 结果输出格式
+    #[arg(long = "format", value_enum, default_value_t = OutputFormat::Json)]
+    pub format: OutputFormat,
+
+    /// 每条记录以 NUL 结尾（配合 `xargs -0`，对 json 格式无效）
+    #[arg(short = '0', long = "print0")]
+    pub print0: bool,
+
+    /// 只输出这些字段（逗号分隔，如 path,size,modified），不存在的字段报错；`path` 对内容匹配即 `full_path`
+    #[arg(long = "fields", value_delimiter = ',')]
+    pub fields: Vec<String>,
+}
+
+/// `--format` 的取值
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// 带 code/msg 的完整 JSON（终端下缩进，管道中紧凑）
+    #[default]
+    Json,
+    /// 每条结果一行 JSON
+    Jsonl,
+    /// 制表符分隔，首行为字段名
+    Tsv,
+    /// 逗号分隔，首行为字段名
+    Csv,
+    /// 每行一个路径
+    Paths,
+}
+
+/// `--preview` 的输出格式
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewFormat {
+    /// 24 位色终端转义（深色主题）
+    Ansi,
+    /// 内联样式的 HTML 片段（浅色主题）
+    Html,
+    /// 带样式的行与片段（浅色主题）
+    Json,
+}
+
+/// 结果状态：`code` 字段与进程退出码取相同的值，`status` 为对应的名称
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Success = 0,
+    /// 查询正常执行但没有结果
+    NoResults = 1,
+    /// 正则或查询语法错误（与 clap 的参数错误共用 2）
+    InvalidQuery = 2,
+    /// 搜索范围、镜像或预览的路径不存在
+    ScopeNotFound = 3,
+    /// 本地索引尚未建立或仍在加载，结果可能不完整
+    IndexNotReady = 4,
+    /// 其他错误（读写失败、服务异常等）
+    Internal = 5,
+}
+
+impl ErrorCode {
+    pub fn exit_code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// 带错误码的 CLI 错误，经 anyhow 传到入口后按 `code` 输出
+#[derive(Debug)]
+pub struct CliError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// 命令行入口：分派子命令，返回进程退出码
+pub fn run(cli: Cli) -> i32 {
+    // 搜索出错时信封里带上查询与范围，并沿用其输出格式
+    let (output_args, query, scope) = match &cli.command {
+        Command::Search(args) | Command::Content(args) => (args.output.clone(), Some(args.query.clone()), args.scope.clone()),
+        _ => (OutputArgs::default(), None, None),
+    };
+
+    let result = match cli.command {
+        // 服务调度器要在主线程阻塞运行，不能放进 tokio 运行时
+        Command::Service { action } => run_service(action),
+        command => tokio::runtime::Runtime::new().map_err(anyhow::Error::from).and_then(|rt| {
+            rt.block_on(async move {
+                match command {
+                    Command::Search(args) => run_cli(args).await,
+                    Command::Content(mut args) => {
+                        args.content = true;
+                        run_cli(args).await
+                    }
+                    Command::Index { action } => run_index(action).await,
+                    Command::Config => print_config(),
+                    Command::Service { .. } => unreachable!(),
+                }
+            })
+        }),
+    };
+
+    match result {
+        Ok(code) => code.exit_code(),
+        Err(e) => {
+            let code = e.downcast_ref::<CliError>().map(|e| e.code).unwrap_or(ErrorCode::Internal);
+            let mut output = json!({
+                "code": code.exit_code(),
+                "status": code,
+                "msg": format!("{:#}", e),
+            });
+            if let Some(query) = query {
+                output["query"] = json!(query);
+                output["scope"] = json!(scope.unwrap_or_default());
+            }
+            if write_output(&output_args, RecordKind::File, &output).is_err() {
+                eprintln!("{:#}", e);
+            }
+            code.exit_code()
+        }
+    }
+}
+
+fn run_service(action: ServiceAction) -> anyhow::Result<ErrorCode> {
+    match action {
+        ServiceAction::Install => {
+            crate::service::install_service()?;
+            print_json(&json!({ "code": 0, "status": ErrorCode::Success, "msg": "服务已安装并启动" }))?;
+        }
+        ServiceAction::Uninstall => {
+            crate::service::uninstall_service()?;
+            print_json(&json!({ "code": 0, "status": ErrorCode::Success, "msg": "服务已卸载" }))?;
+        }
+        ServiceAction::Run => crate::service::run()?,
+    }
+    Ok(ErrorCode::Success)
+}
+
+async fn run_index(action: IndexAction) -> anyhow::Result<ErrorCode> {
+    match action {
+        IndexAction::Build { deleted, streams, archives, fulltext } => {
+            let start = std::time::Instant::now();
+            let searcher = LocalNtfsSearcher::new()
+                .with_deleted(deleted)
+                .with_streams(streams)
+                .with_archives(archives);
+            let count = searcher.rebuild().await?;
+            let mut output = json!({
+                "code": 0,
+                "status": ErrorCode::Success,
+                "msg": "success",
+                "type": "index_build",
+                "count": count,
+                "elapsed_ms": start.elapsed().as_millis() as u64,
+            });
+            if fulltext {
+                let stats = crate::fulltext::FullTextIndex::open()?.update(&crate::config::GLOBAL_CONFIG.local_work_dirs)?;
+                output["fulltext"] = serde_json::to_value(stats)?;
+                output["elapsed_ms"] = json!(start.elapsed().as_millis() as u64);
+            }
+            print_json(&output)?;
+            Ok(ErrorCode::Success)
+        }
+        IndexAction::Status => {
+            let local_path = crate::config::local_index_db_path();
+            let fulltext_path = crate::config::fulltext_db_path();
+            let local_entries = if local_path.exists() { LocalNtfsSearcher::new().cached_count() } else { None };
+            let fulltext_files = if fulltext_path.exists() {
+                crate::fulltext::FullTextIndex::open()?.file_count().ok()
+            } else {
+                None
+            };
+            print_json(&json!({
+                "code": 0,
+                "status": ErrorCode::Success,
+                "msg": "success",
+                "type": "index_status",
+                "local_index": db_status(&local_path, local_entries),
+                "fulltext": db_status(&fulltext_path, fulltext_files),
+                "service": {
+                    "endpoint": crate::ipc::endpoint(),
+                    "running": service_running().await,
+                },
+            }))?;
+            Ok(ErrorCode::Success)
+        }
+        IndexAction::Export { output } => {
+            let searcher = LocalNtfsSearcher::new();
+            searcher.load_all_drives().await?;
+            let entries = searcher.entries().await;
+            if entries.is_empty() {
+                return Err(CliError::new(ErrorCode::IndexNotReady, "本地索引为空，请先运行 `index build`").into());
+            }
+            output.check_fields(RecordKind::File)?;
+            write_output(&output, RecordKind::File, &json!({
+                "code": 0,
+                "status": ErrorCode::Success,
+                "msg": "success",
+                "type": "index_export",
+                "results": entries,
+            }))?;
+            Ok(ErrorCode::Success)
+        }
+    }
+}
+
+// 索引文件的路径、大小、修改时间与条目数
+fn db_status(path: &std::path::Path, entries: Option<u64>) -> serde_json::Value {
+    let metadata = std::fs::metadata(path).ok();
+    json!({
+        "path": path.to_string_lossy(),
+        "exists": metadata.is_some(),
+        "size": metadata.as_ref().map(|m| m.len()),
+        "modified": metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+        "entries": entries,
+    })
+}
+
+// 服务没运行时连接会立即失败，运行中但无响应时按超时处理
+async fn service_running() -> bool {
+    let ping = crate::types::SearchRequest {
+        query: String::new(),
+        limit: 1,
+        max_results: 1,
+        scope: None,
+        extensions: None,
+        sort: SortKey::Score,
+        reverse: false,
+    };
+    let request = crate::ipc::client_request(&ping);
+    matches!(tokio::time::timeout(std::time::Duration::from_secs(1), request).await, Ok(Ok(_)))
+}
+
+fn print_config() -> anyhow::Result<ErrorCode> {
+    use crate::config::*;
+    print_json(&json!({
+        "code": 0,
+        "status": ErrorCode::Success,
+        "msg": "success",
+        "type": "config",
+        "data_dir": data_dir().to_string_lossy(),
+        "local_index_db": local_index_db_path().to_string_lossy(),
+        "fulltext_db": fulltext_db_path().to_string_lossy(),
+        "thumbnail_cache_dir": thumbnail_cache_dir().to_string_lossy(),
+        "backup_dir": backup_dir().to_string_lossy(),
+        "service_endpoint": crate::ipc::endpoint(),
+        "local_work_dirs": GLOBAL_CONFIG.local_work_dirs,
+        "local_max_cache": GLOBAL_CONFIG.local_max_cache,
+        "es_instance": ES_INSTANCE,
+        "limits": {
+            "max_grep_file_size": MAX_GREP_FILE_SIZE,
+            "max_grep_per_file": MAX_GREP_PER_FILE,
+            "max_grep_total": MAX_GREP_TOTAL,
+            "max_archive_scan_size": MAX_ARCHIVE_SCAN_SIZE,
+            "max_preview_bytes": MAX_PREVIEW_BYTES,
+            "max_preview_lines": MAX_PREVIEW_LINES,
+        },
+    }))?;
+    Ok(ErrorCode::Success)
+}
+
+// 搜索入口
+pub async fn run_cli(args: CliArgs) -> anyhow::Result<ErrorCode> {
+    // 先解析模板，语法错误时不必等搜索完成
+    let exec = match (&args.exec, &args.exec_batch) {
+        (Some(t), _) | (None, Some(t)) => Some(
+            crate::exec::CommandTemplate::parse(t).map_err(|e| CliError::new(ErrorCode::InvalidQuery, format!("{:#}", e)))?,
+        ),
+        (None, None) => None,
+    };
+    for (label, path) in [("搜索范围", args.scope.as_deref().map(std::path::Path::new)), ("镜像", args.image.as_deref()), ("预览路径", args.preview.as_deref())] {
+        if let Some(path) = path.filter(|p| !p.exists()) {
+            return Err(CliError::new(ErrorCode::ScopeNotFound, format!("{}不存在: {}", label, path.display())).into());
+        }
+    }
+
+    if let Some(path) = &args.preview {
+        let preview = crate::preview::preview_file(path, args.preview_format == PreviewFormat::Ansi)?;
+        match args.preview_format {
+            PreviewFormat::Ansi => print_stdout(&preview.to_ansi())?,
+            PreviewFormat::Html => print_stdout(&preview.to_html())?,
+            PreviewFormat::Json => print_json(&serde_json::to_value(&preview)?)?,
+        }
+        return Ok(ErrorCode::Success);
+    }
+
     let rt_config = RuntimeConfig {
-        search_scope: args.scope.unwrap_or_default(),
+        search_scope: args.scope.clone().unwrap_or_default(),
         is_content_search: args.content,
-        max_results: args.max_results,
+        max_results: args.max_results.unwrap_or(if exec.is_some() {
+            crate::config::GLOBAL_CONFIG.local_max_cache
+        } else {
+            DEFAULT_MAX_RESULTS
+        }),
+        include_archives: args.archives,
+    };
+    let sorting = sorting(&args);
+    let candidate_config = candidate_config(&args, &rt_config);
+
+    // `content:` 组合查询：文件名部分筛候选，内容只在候选文件中匹配
+    let local_query = LocalQuery::parse(&args.query);
+    check_combined(&args, &local_query)?;
+    let record_kind = RecordKind::of(&args, &local_query);
+    args.output.check_fields(record_kind)?;
+    let query_type = if local_query.content.is_some() {
+        "combined"
+    } else if rt_config.is_content_search && args.replace.is_some() {
+        "replace"
+    } else if rt_config.is_content_search {
+        "content"
+    } else {
+        "filename"
     };
 
+    // 内容搜索时跳过的文件（明细与按原因计数）
+    let mut skipped = Vec::new();
+    let mut skip_counts = std::collections::BTreeMap::new();
+    // 实际使用的后端，以及文件名索引是否已就绪（不依赖索引的搜索视为就绪）
+    let mut backend = "content_scan";
+    let mut index_ready = true;
+    let mut not_ready_msg = "本地索引尚未就绪，请先运行 `index build` 或等待服务加载完成";
+
     // 执行搜索
-    let results_json = if rt_config.is_content_search {
+    let results_json = if let Some(image) = &args.image {
+        // NTFS 镜像：直接解析 MFT，不依赖本机驱动器
+        if rt_config.is_content_search {
+            return Err(CliError::new(ErrorCode::InvalidQuery, "NTFS 镜像暂不支持内容搜索").into());
+        }
+        backend = "ntfs_image";
+        let searcher = LocalNtfsSearcher::new()
+            .with_deleted(args.deleted)
+            .with_streams(args.streams);
+        searcher.load_image(image, args.image_offset).await?;
+        serde_json::to_value(searcher.search_sorted(&args.query, rt_config.max_results, args.sort, args.reverse).await)?
+    } else if let Some(content) = &local_query.content {
+        let searcher = content_searcher(&args);
+        if let Err(e) = searcher.build_matcher(content) {
+            let output = json!({
+                "code": ErrorCode::InvalidQuery.exit_code(),
+                "status": ErrorCode::InvalidQuery,
+                "msg": e.to_string(),
+                "query": args.query,
+                "scope": rt_config.search_scope,
+                "type": query_type,
+                "error": e,
+            });
+            write_output(&args.output, record_kind, &output)?;
+            return Ok(ErrorCode::InvalidQuery);
+        }
+        let candidates = if !local_query.has_filename_part() {
+            None
+        } else if rt_config.search_scope.is_empty() {
+            // 候选集要完整，直接查本地索引，按缓存上限取
+            backend = "local_index";
+            let ntfs = LocalNtfsSearcher::new()
+                .with_deleted(args.deleted)
+                .with_streams(args.streams)
+                .with_archives(args.archives);
+            index_ready = matches!(ntfs.load_all_drives().await, Ok(count) if count > 0);
+            Some(ntfs.search(&args.query, crate::config::GLOBAL_CONFIG.local_max_cache).await)
+        } else {
+            backend = "custom_path";
+            let scan_config = RuntimeConfig {
+                max_results: crate::config::GLOBAL_CONFIG.local_max_cache,
+                ..rt_config.clone()
+            };
+            Some(crate::custom_path::search_custom_path(&args.query, &scan_config).await?)
+        };
+        let entries = searcher.search_entries(content, candidates, &candidate_config)?;
+        serde_json::to_value(sort::top_k(entries, rt_config.max_results, args.sort, args.reverse, |e| entry_value(e, args.sort)))?
+    } else if rt_config.is_content_search {
         // 内容搜索
-        let searcher = ContentSearcher;
-        let results = searcher.search(&args.query, &rt_config)?
-            .into_iter()
-            .take(rt_config.max_results)
-            .collect::<Vec<_>>();
-        serde_json::to_value(results)?
+        let roots = if rt_config.search_scope.is_empty() {
+            crate::config::GLOBAL_CONFIG.local_work_dirs.clone()
+        } else {
+            vec![rt_config.search_scope.clone()]
+        };
+        if args.fulltext_update {
+            crate::fulltext::FullTextIndex::open()?.update(&roots)?;
+        }
+        if args.fulltext || args.fulltext_update {
+            // 全文索引未建立或未覆盖范围时退回全量扫描，结果仍完整，但如实报告索引状态
+            index_ready = matches!(crate::fulltext::FullTextIndex::open_covering(&roots), Ok(Some(_)));
+            not_ready_msg = "全文索引尚未建立或未覆盖搜索范围，已改为全量扫描；可用 `--fulltext-update` 建立";
+        }
+        let searcher = content_searcher(&args);
+
+        // 正则错误作为结果输出，方便 AI 调用方修正查询
+        if let Err(e) = searcher.build_matcher(&args.query) {
+            let output = json!({
+                "code": ErrorCode::InvalidQuery.exit_code(),
+                "status": ErrorCode::InvalidQuery,
+                "msg": e.to_string(),
+                "query": args.query,
+                "scope": rt_config.search_scope,
+                "type": query_type,
+                "error": e,
+            });
+            write_output(&args.output, record_kind, &output)?;
+            return Ok(ErrorCode::InvalidQuery);
+        }
+        if let Some(replacement) = &args.replace {
+            let outcome = Replacer::new(searcher, replacement)
+                .with_apply(args.apply)
+                .run(&args.query, &rt_config)?;
+            serde_json::to_value(outcome)?
+        } else if args.stream {
+            return run_stream(searcher, &args.query, &rt_config);
+        } else {
+            let outcome = searcher.search(&args.query, &candidate_config)?;
+            if outcome.used_index {
+                backend = "fulltext_index";
+            }
+            skipped = outcome.skipped;
+            skip_counts = outcome.skip_counts;
+            let (key, reverse, limit) = (args.sort, args.reverse, rt_config.max_results);
+            if args.group {
+                let files = if sorting {
+                    sort::top_k(outcome.files, limit, key, reverse, |f| path_value(&f.full_path, f.score, key))
+                } else {
+                    outcome.files
+                };
+                serde_json::to_value(files)?
+            } else {
+                let results = if sorting {
+                    sort::top_k(outcome.matches, limit, key, reverse, |m| match_value(m, key))
+                } else {
+                    outcome.matches.into_iter().take(limit).collect()
+                };
+                serde_json::to_value(results)?
+            }
+        }
     } else {
         // 文件名搜索
         let results = if rt_config.search_scope.is_empty() {
             // 1. 优先尝试 IPC (Everything Service 模式)
             let req = crate::types::SearchRequest {
                 query: args.query.clone(),
-                limit: args.max_results,
-                max_results: args.max_results,
+                limit: rt_config.max_results,
+                max_results: rt_config.max_results,
                 scope: None,
                 extensions: None,
+                sort: args.sort,
+                reverse: args.reverse,
             };
-            if let Ok(response) = crate::ipc::client_request(&req).await {
-                if response.success {
-                    response.results.into_iter().map(|r| crate::types::FileEntry {
-                        name: r.name,
-                        path: r.path,
-                        extension: r.extension,
-                        size: r.size,
-                        modified: r.modified,
-                        is_dir: r.is_dir,
-                        drive: ' ',
-                        score: 0.0,
-                    }).collect::<Vec<_>>()
-                } else {
-                    Vec::new()
+            let response = if needs_local_scan(&args, &local_query) {
+                None
+            } else {
+                crate::ipc::client_request(&req).await.ok()
+            };
+            if let Some(response) = response {
+                backend = "ipc_service";
+                if !response.success {
+                    let msg = response.error.unwrap_or_else(|| "服务返回失败".to_string());
+                    return Err(CliError::new(ErrorCode::Internal, msg).into());
                 }
+                index_ready = response.index_ready;
+                response.results.into_iter().map(|r| crate::types::FileEntry {
+                    drive: ' ',
+                    score: 0.0,
+                    ..r
+                }).collect::<Vec<_>>()
             } else {
                 // 2. 降级到本地模式 (CLI 模式直接初始化并等待索引加载)
-                let searcher = LocalNtfsSearcher::new();
-                let _ = searcher.load_all_drives().await;
-                searcher.search(&args.query, rt_config.max_results).await
+                backend = "local_index";
+                let searcher = LocalNtfsSearcher::new()
+                    .with_deleted(args.deleted)
+                    .with_streams(args.streams)
+                    .with_archives(args.archives);
+                index_ready = matches!(searcher.load_all_drives().await, Ok(count) if count > 0);
+                searcher.search_sorted(&args.query, rt_config.max_results, args.sort, args.reverse).await
             }
+        } else if sorting {
+            // 自定义路径（U盘）walkdir扫描，按缓存上限取候选后排序
+            backend = "custom_path";
+            let scan_config = RuntimeConfig {
+                max_results: crate::config::GLOBAL_CONFIG.local_max_cache,
+                ..rt_config.clone()
+            };
+            let entries = crate::custom_path::search_custom_path(&args.query, &scan_config).await?;
+            sort::top_k(entries, rt_config.max_results, args.sort, args.reverse, |e| entry_value(e, args.sort))
         } else {
             // 自定义路径（U盘）walkdir扫描
+            backend = "custom_path";
             crate::custom_path::search_custom_path(&args.query, &rt_config).await?
         };
         serde_json::to_value(results)?
     };
 
-    let output = json!({
-        "code": 0,
-        "msg": "success",
+    let found = match &results_json {
+        serde_json::Value::Array(items) => !items.is_empty(),
+        // 替换结果按是否有改动判断
+        other => other["changes"].as_array().is_none_or(|changes| !changes.is_empty()),
+    };
+    let code = if found {
+        ErrorCode::Success
+    } else if !index_ready {
+        ErrorCode::IndexNotReady
+    } else {
+        ErrorCode::NoResults
+    };
+    let msg = match code {
+        ErrorCode::Success => "success",
+        ErrorCode::IndexNotReady => not_ready_msg,
+        _ => "未找到结果",
+    };
+
+    let mut output = json!({
+        "code": code.exit_code(),
+        "status": code,
+        "msg": msg,
         "query": args.query,
         "scope": rt_config.search_scope,
-        "type": if rt_config.is_content_search { "content" } else { "filename" },
+        "type": query_type,
+        "backend": backend,
+        "index_ready": index_ready,
         "results": results_json
     });
+    if !skipped.is_empty() {
+        output["skipped"] = serde_json::to_value(&skipped)?;
+    }
+    if !skip_counts.is_empty() {
+        output["skip_counts"] = serde_json::to_value(&skip_counts)?;
+    }
+
+    if let Some(template) = exec {
+        return run_exec(&args, &template, &output, code, rt_config.max_results);
+    }
 
     // 输出JSON（AI易解析）
-    println!("{}", serde_json::to_string_pretty(&output)?);
-    Ok(())
+    write_output(&args.output, record_kind, &output)?;
+    Ok(code)
+}
+
+// 用结果路径执行命令：标准输出留给命令本身，汇总信息写到 stderr
+fn run_exec(
+    args: &CliArgs,
+    template: &crate::exec::CommandTemplate,
+    output: &serde_json::Value,
+    code: ErrorCode,
+    limit: usize,
+) -> anyhow::Result<ErrorCode> {
+    if code != ErrorCode::Success {
+        eprintln!("{}", output["msg"].as_str().unwrap_or_default());
+        return Ok(code);
+    }
+
+    // 内容匹配同一文件可能有多行，路径去重后保持原顺序
+    let records = match &output["results"] {
+        serde_json::Value::Array(items) => items.as_slice(),
+        _ => &[],
+    };
+    let mut seen = std::collections::HashSet::new();
+    let paths: Vec<String> = records
+        .iter()
+        .filter_map(|r| field(r, "path").and_then(|v| v.as_str()))
+        .filter(|p| seen.insert(*p))
+        .map(str::to_string)
+        .collect();
+    if records.len() >= limit {
+        eprintln!("结果已达上限 {} 条，后面的匹配未执行；可用 -m 调大", limit);
+    }
+
+    let summary = if args.exec_batch.is_some() {
+        template.run_batch(&paths, crate::config::EXEC_BATCH_SIZE)
+    } else {
+        let jobs = args
+            .jobs
+            .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+            .max(1);
+        template.run_each(&paths, jobs)?
+    };
+    if summary.failed > 0 {
+        eprintln!("{}/{} 条命令执行失败", summary.failed, summary.commands);
+        return Ok(ErrorCode::Internal);
+    }
+    Ok(ErrorCode::Success)
+}
+
+// 终端下缩进便于阅读，管道中输出紧凑 JSON 节省体积
+fn print_json(value: &serde_json::Value) -> anyhow::Result<()> {
+    use std::io::IsTerminal;
+    let text = if std::io::stdout().is_terminal() {
+        serde_json::to_string_pretty(value)?
+    } else {
+        serde_json::to_string(value)?
+    };
+    print_stdout(&format!("{}\n", text))
+}
+
+// 写到标准输出；下游管道提前关闭（如 `| head`）不算错误，`println!` 此时会 panic
+fn print_stdout(text: &str) -> anyhow::Result<()> {
+    use std::io::Write;
+    let mut out = std::io::stdout().lock();
+    match out.write_all(text.as_bytes()).and_then(|_| out.flush()) {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        other => Ok(other?),
+    }
+}
+
+/// 输出记录的类型，决定表格的默认列与 `--fields` 可选的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordKind {
+    /// 文件名搜索、组合查询与索引导出的文件条目
+    File,
+    /// 内容搜索的单条匹配
+    ContentMatch,
+    /// 内容搜索按文件分组（`--group`）
+    ContentFile,
+    /// 替换的逐文件改动
+    Change,
+}
+
+/// `content:` 组合查询只输出文件列表，不支持替换、流式输出和按文件分组
+fn check_combined(args: &CliArgs, local_query: &LocalQuery) -> Result<(), CliError> {
+    if local_query.content.is_none() {
+        return Ok(());
+    }
+    let unsupported: Vec<&str> = [("--replace", args.replace.is_some()), ("--stream", args.stream), ("--group", args.group)]
+        .into_iter()
+        .filter_map(|(flag, set)| set.then_some(flag))
+        .collect();
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::new(ErrorCode::InvalidQuery, format!("content: 组合查询不支持 {}", unsupported.join("、"))))
+    }
+}
+
+/// 服务只持有普通条目的索引：开启可选扫描项或查询已删除文件、数据流时不能走 IPC
+fn needs_local_scan(args: &CliArgs, local_query: &LocalQuery) -> bool {
+    let scan_options = NtfsScanOptions {
+        include_deleted: args.deleted,
+        include_streams: args.streams,
+        include_archives: args.archives,
+    };
+    scan_options.any() || local_query.deleted || local_query.ads
+}
+
+impl RecordKind {
+    fn of(args: &CliArgs, local_query: &LocalQuery) -> Self {
+        if local_query.content.is_some() || !args.content {
+            Self::File
+        } else if args.replace.is_some() {
+            Self::Change
+        } else if args.group {
+            Self::ContentFile
+        } else {
+            Self::ContentMatch
+        }
+    }
+
+    /// tsv/csv 未指定 `--fields` 时的列，与记录是否带可选字段无关
+    fn default_columns(self) -> &'static [&'static str] {
+        match self {
+            Self::File => &["name", "path", "extension", "size", "modified", "is_dir", "drive", "score", "kind", "recoverable"],
+            Self::ContentMatch => &["full_path", "line_number", "line_content", "score", "locator", "page", "encoding"],
+            Self::ContentFile => &["full_path", "score"],
+            Self::Change => &["path", "encoding", "line_ending", "replacements"],
+        }
+    }
+
+    /// `--fields` 可用的全部字段（含数组字段；`path` 对内容匹配即 `full_path`）
+    fn fields(self) -> &'static [&'static str] {
+        match self {
+            Self::File => &[
+                "name", "path", "extension", "size", "modified", "is_dir", "drive", "score", "kind", "recoverable", "links", "matches",
+            ],
+            Self::ContentMatch => &[
+                "full_path", "path", "line_number", "line_content", "score", "score_detail", "locator", "page", "encoding", "offsets",
+                "context_before", "context_after",
+            ],
+            Self::ContentFile => &["full_path", "path", "score", "matches"],
+            Self::Change => &["path", "encoding", "line_ending", "replacements", "diff"],
+        }
+    }
+}
+
+impl OutputArgs {
+    /// 拒绝该类记录没有的字段，避免拼错的字段静默输出空列
+    fn check_fields(&self, kind: RecordKind) -> Result<(), CliError> {
+        match self.fields.iter().find(|f| !kind.fields().contains(&f.as_str())) {
+            Some(unknown) => Err(CliError::new(
+                ErrorCode::InvalidQuery,
+                format!("未知字段: {}（可用: {}）", unknown, kind.fields().join(",")),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 按 `--format`、`--fields`、`-0` 输出；除 json 外只输出结果记录，出错时把信息写到 stderr
+fn write_output(args: &OutputArgs, kind: RecordKind, output: &serde_json::Value) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let records: Vec<serde_json::Value> = match &output["results"] {
+        serde_json::Value::Array(items) => items.iter().map(|r| select_fields(r, &args.fields)).collect(),
+        // 替换结果是单个对象，逐文件的改动才是记录
+        serde_json::Value::Object(obj) if obj.contains_key("changes") => obj["changes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|r| select_fields(r, &args.fields))
+            .collect(),
+        serde_json::Value::Null => Vec::new(),
+        other => vec![select_fields(other, &args.fields)],
+    };
+
+    if args.format == OutputFormat::Json {
+        let mut output = output.clone();
+        if !args.fields.is_empty() && output["results"].is_array() {
+            output["results"] = serde_json::Value::Array(records);
+        }
+        return print_json(&output);
+    }
+    if output["code"] != 0 {
+        eprintln!("{}", output["msg"].as_str().unwrap_or("未知错误"));
+        return Ok(());
+    }
+
+    let end = if args.print0 { "\0" } else { "\n" };
+    let columns = if args.fields.is_empty() {
+        kind.default_columns().iter().map(|c| c.to_string()).collect()
+    } else {
+        args.fields.clone()
+    };
+
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    let written = (|| -> std::io::Result<()> {
+        match args.format {
+            OutputFormat::Json => unreachable!(),
+            OutputFormat::Jsonl => {
+                for record in &records {
+                    write!(out, "{}{}", record, end)?;
+                }
+            }
+            OutputFormat::Paths => {
+                for record in &records {
+                    if let Some(path) = field(record, "path").and_then(|p| p.as_str()) {
+                        write!(out, "{}{}", path, end)?;
+                    }
+                }
+            }
+            OutputFormat::Tsv | OutputFormat::Csv => {
+                let csv = args.format == OutputFormat::Csv;
+                let row = |cells: Vec<String>| {
+                    if csv {
+                        cells.iter().map(|c| csv_cell(c)).collect::<Vec<_>>().join(",")
+                    } else {
+                        cells.iter().map(|c| c.replace(['\t', '\r', '\n'], " ")).collect::<Vec<_>>().join("\t")
+                    }
+                };
+                write!(out, "{}{}", row(columns.clone()), end)?;
+                for record in &records {
+                    let cells = columns.iter().map(|c| field(record, c).map(cell_text).unwrap_or_default()).collect();
+                    write!(out, "{}{}", row(cells), end)?;
+                }
+            }
+        }
+        out.flush()
+    })();
+    // 下游管道提前关闭（如 `| head`）不算错误
+    match written {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        other => Ok(other?),
+    }
+}
+
+// 取字段；`path` 对内容匹配等只有 `full_path` 的记录同样有效
+fn field<'a>(record: &'a serde_json::Value, name: &str) -> Option<&'a serde_json::Value> {
+    record.get(name).or_else(|| if name == "path" { record.get("full_path") } else { None })
+}
+
+fn select_fields(record: &serde_json::Value, fields: &[String]) -> serde_json::Value {
+    if fields.is_empty() || !record.is_object() {
+        return record.clone();
+    }
+    let selected = fields
+        .iter()
+        .filter_map(|f| field(record, f).map(|v| (f.clone(), v.clone())))
+        .collect();
+    serde_json::Value::Object(selected)
+}
+
+fn cell_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn csv_cell(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// 按其他字段或反向排序时，结果要从全部匹配中选出，不能只看最先找到的一批
+fn sorting(args: &CliArgs) -> bool {
+    args.sort != SortKey::Score || args.reverse
+}
+
+// 排序或执行命令时内容搜索不受 MAX_GREP_TOTAL 限制
+fn collect_all(args: &CliArgs) -> bool {
+    sorting(args) || args.exec.is_some() || args.exec_batch.is_some()
+}
+
+// 排序时收集全部匹配，再选出前 max_results 条
+fn candidate_config(args: &CliArgs, rt_config: &RuntimeConfig) -> RuntimeConfig {
+    if sorting(args) {
+        RuntimeConfig { max_results: usize::MAX, ..rt_config.clone() }
+    } else {
+        rt_config.clone()
+    }
+}
+
+// 按命令行参数构建内容搜索器
+fn content_searcher(args: &CliArgs) -> ContentSearcher {
+    ContentSearcher::new()
+        .with_max_total(if collect_all(args) { usize::MAX } else { crate::config::MAX_GREP_TOTAL })
+        .with_context(args.before.unwrap_or(args.context), args.after.unwrap_or(args.context))
+        .with_grouping(args.group)
+        .with_extensions(&args.ext, &args.exclude_ext)
+        .with_max_per_file(args.max_per_file)
+        .with_index(args.fulltext || args.fulltext_update)
+        .with_time_budget(args.timeout.map(std::time::Duration::from_millis))
+        .with_fixed_strings(args.fixed_strings)
+        .with_case(if args.ignore_case {
+            CaseMode::Insensitive
+        } else if args.smart_case {
+            CaseMode::Smart
+        } else {
+            CaseMode::Sensitive
+        })
+        .with_whole_word(args.word)
+        .with_multiline(args.multiline)
+        .with_score_detail(args.debug_score)
+}
+
+// 流式内容搜索：每条匹配一行 JSON，Ctrl+C 取消后仍输出汇总行
+fn run_stream(searcher: ContentSearcher, query: &str, rt_config: &RuntimeConfig) -> anyhow::Result<ErrorCode> {
+    use std::io::Write;
+
+    let cancel = CancelToken::new();
+    let ctrl_c = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            ctrl_c.cancel();
+        }
+    });
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let worker = {
+        let query = query.to_string();
+        let rt_config = rt_config.clone();
+        let cancel = cancel.clone();
+        std::thread::spawn(move || searcher.search_stream(&query, &rt_config, tx, cancel))
+    };
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    let mut matched = 0usize;
+    for m in rx {
+        matched += 1;
+        let mut line = serde_json::to_value(&m)?;
+        line["type"] = json!("match");
+        if writeln!(out, "{}", line).and_then(|_| out.flush()).is_err() {
+            // 下游管道已关闭（如 `| head`），停止搜索
+            cancel.cancel();
+            break;
+        }
+    }
+
+    let summary = worker.join().map_err(|_| anyhow::anyhow!("内容搜索线程异常退出"))??;
+    let code = if matched > 0 { ErrorCode::Success } else { ErrorCode::NoResults };
+    let line = json!({
+        "type": "summary",
+        "code": code.exit_code(),
+        "status": code,
+        "backend": if summary.used_index { "fulltext_index" } else { "content_scan" },
+        "query": query,
+        "scope": rt_config.search_scope,
+        "skipped": summary.skipped,
+        "skip_counts": summary.skip_counts,
+        "used_index": summary.used_index,
+        "cancelled": summary.cancelled,
+        "timed_out": summary.timed_out,
+    });
+    let _ = writeln!(out, "{}", line);
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("starsearch-cli-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn search_args(argv: &[&str]) -> CliArgs {
+        match Cli::parse_from(argv).command {
+            Command::Search(args) => args,
+            Command::Content(mut args) => {
+                args.content = true;
+                args
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn content_sort_considers_every_match() {
+        let dir = temp_dir("sort");
+        for i in 0..1000 {
+            std::fs::write(dir.join(format!("{}.txt", i)), "needle\n").unwrap();
+        }
+        let big = dir.join("big.txt");
+        std::fs::write(&big, format!("needle\n{}", "x".repeat(100_000))).unwrap();
+
+        let scope = dir.to_string_lossy().to_string();
+        let args = search_args(&["starsearch", "content", "-q", "needle", "--sort", "size", "-m", "1", "--scope", &scope]);
+        let rt_config = RuntimeConfig { search_scope: scope, is_content_search: true, max_results: 1, include_archives: false };
+        let outcome = content_searcher(&args).search(&args.query, &candidate_config(&args, &rt_config)).unwrap();
+        assert_eq!(outcome.matches.len(), 1001);
+        let top = sort::top_k(outcome.matches, 1, args.sort, args.reverse, |m| match_value(m, args.sort));
+        assert_eq!(top[0].full_path, big.to_string_lossy());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn csv_cells_are_quoted_only_when_needed() {
+        assert_eq!(csv_cell("plain"), "plain");
+        assert_eq!(csv_cell("a,b"), "\"a,b\"");
+        assert_eq!(csv_cell(r#"say "hi""#), r#""say ""hi""""#);
+        assert_eq!(csv_cell("line1\nline2"), "\"line1\nline2\"");
+        assert_eq!(csv_cell("cr\r"), "\"cr\r\"");
+        assert_eq!(csv_cell(""), "");
+    }
+
+    #[test]
+    fn fields_are_checked_against_the_record_kind() {
+        let kind = |argv: &[&str]| {
+            let args = search_args(argv);
+            RecordKind::of(&args, &LocalQuery::parse(&args.query))
+        };
+        assert_eq!(kind(&["starsearch", "search", "-q", "a"]), RecordKind::File);
+        assert_eq!(kind(&["starsearch", "content", "-q", "a"]), RecordKind::ContentMatch);
+        assert_eq!(kind(&["starsearch", "content", "-q", "a", "--group"]), RecordKind::ContentFile);
+        assert_eq!(kind(&["starsearch", "content", "-q", "a", "--replace", "b"]), RecordKind::Change);
+
+        let args = search_args(&["starsearch", "content", "-q", "a", "--fields", "path,line_number"]);
+        assert!(args.output.check_fields(RecordKind::ContentMatch).is_ok());
+        let args = search_args(&["starsearch", "search", "-q", "a", "--fields", "path,szie"]);
+        let err = args.output.check_fields(RecordKind::File).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidQuery);
+        assert!(err.message.contains("szie"));
+    }
+
+    #[test]
+    fn combined_query_rejects_per_match_options() {
+        let check = |argv: &[&str]| {
+            let args = search_args(argv);
+            check_combined(&args, &LocalQuery::parse(&args.query))
+        };
+        assert!(check(&["starsearch", "search", "-q", "ext:rs content:TODO"]).is_ok());
+        assert!(check(&["starsearch", "content", "-q", "TODO", "--group"]).is_ok());
+        for flag in [&["--replace", "DONE"][..], &["--stream"], &["--group"]] {
+            let argv: Vec<&str> = ["starsearch", "search", "-q", "ext:rs content:TODO"].into_iter().chain(flag.iter().copied()).collect();
+            let err = check(&argv).unwrap_err();
+            assert_eq!(err.code, ErrorCode::InvalidQuery);
+            assert!(err.message.contains(flag[0]));
+        }
+    }
+
+    #[test]
+    fn optional_entries_bypass_the_service() {
+        let local = |argv: &[&str]| {
+            let args = search_args(argv);
+            needs_local_scan(&args, &LocalQuery::parse(&args.query))
+        };
+        assert!(!local(&["starsearch", "search", "-q", "report"]));
+        assert!(local(&["starsearch", "search", "-q", "report", "--deleted"]));
+        assert!(local(&["starsearch", "search", "-q", "report", "--streams"]));
+        assert!(local(&["starsearch", "search", "-q", "report", "--archives"]));
+        assert!(local(&["starsearch", "search", "-q", "deleted: report"]));
+        assert!(local(&["starsearch", "search", "-q", "ads: report"]));
+    }
+
+    #[test]
+    fn default_columns_cover_every_kind() {
+        for kind in [RecordKind::File, RecordKind::ContentMatch, RecordKind::ContentFile, RecordKind::Change] {
+            assert!(kind.default_columns().iter().all(|c| kind.fields().contains(c)));
+        }
+        // 第一条记录缺少可选字段时表头不变
+        let entry = serde_json::to_value(crate::types::FileEntry::default()).unwrap();
+        assert!(entry.get("recoverable").is_none());
+        assert!(RecordKind::File.default_columns().contains(&"recoverable"));
+    }
 }
@@ -0,0 +1,36 @@
+#![allow(dead_code)]
+mod archive;
+mod cli;
+mod config;
+mod content_search;
+mod custom_path;
+mod encoding;
+mod exec;
+mod extract;
+mod fulltext;
+mod hash;
+mod indexer;
+mod ipc;
+mod ntfs_search;
+mod pe;
+mod preview;
+mod query;
+mod ranking;
+mod replace;
+mod service;
+mod sort;
+mod types;
+
+use clap::Parser;
+
+fn main() {
+    // 日志写到 stderr，stdout 只留给结果
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::WARN)
+        .with_writer(std::io::stderr)
+        .with_ansi(false)
+        .init();
+
+    // 退出码与输出中的 code 一致，见 cli::ErrorCode
+    std::process::exit(cli::run(cli::Cli::parse()));
+}
@@ -13,6 +13,8 @@ pub struct RuntimeConfig {
     pub search_scope: String,
     pub is_content_search: bool,
     pub max_results: usize,
+    /// 是否把压缩包成员作为虚拟路径一并搜索
+    pub include_archives: bool,
 }
 
 #[allow(dead_code)]
@@ -22,7 +24,11 @@ pub struct GlobalConfig {
 }
 
 pub static GLOBAL_CONFIG: once_cell::sync::Lazy<GlobalConfig> = once_cell::sync::Lazy::new(|| GlobalConfig {
+    #[cfg(windows)]
     local_work_dirs: vec!["C:\\".to_string(), "D:\\".to_string()], // 默认扫描 C 和 D 盘
+    // 其他平台没有盘符，默认扫描用户目录
+    #[cfg(not(windows))]
+    local_work_dirs: vec![dirs::home_dir().unwrap_or_else(|| PathBuf::from("/")).to_string_lossy().to_string()],
     local_max_cache: 100_000,
 });
 
@@ -34,10 +40,42 @@ pub const MAX_PREVIEW_BYTES: u64 = 512 * 1024;
 /// 预览最大行数
 pub const MAX_PREVIEW_LINES: usize = 300;
 
+#[allow(dead_code)]
+/// 二进制文件十六进制预览的字节数
+pub const MAX_HEX_PREVIEW_BYTES: usize = 4 * 1024;
+
+#[allow(dead_code)]
+/// 目录预览列出的子项数
+pub const MAX_FOLDER_PREVIEW_CHILDREN: usize = 200;
+
+#[allow(dead_code)]
+/// 压缩包预览列出的成员数
+pub const MAX_ARCHIVE_PREVIEW_MEMBERS: usize = 500;
+
+#[allow(dead_code)]
+/// 目录预览从索引读取的子项上限，用于汇总数量与大小
+pub const MAX_FOLDER_CHILDREN_SCAN: usize = 10_000;
+
+#[allow(dead_code)]
+/// 生成缩略图的原图体积上限
+pub const MAX_THUMBNAIL_SOURCE_BYTES: u64 = 64 * 1024 * 1024;
+
+#[allow(dead_code)]
+/// 缩略图磁盘缓存的总大小上限，超出后删除最久未用的缩略图
+pub const MAX_THUMBNAIL_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
 #[allow(dead_code)]
 /// 内容搜索最大文件大小
 pub const MAX_GREP_FILE_SIZE: u64 = 5 * 1024 * 1024;
 
+#[allow(dead_code)]
+/// 内容搜索中 Office/ODF/PDF 文档的最大文件大小（压缩格式，提取后的文本通常数倍于此）
+pub const MAX_GREP_DOCUMENT_SIZE: u64 = 32 * 1024 * 1024;
+
+#[allow(dead_code)]
+/// 文档包内单个 XML 部件解压后的大小上限（防止压缩炸弹撑爆内存）
+pub const MAX_DOCUMENT_PART_SIZE: u64 = 128 * 1024 * 1024;
+
 #[allow(dead_code)]
 /// 内容搜索单文件最大匹配数
 pub const MAX_GREP_PER_FILE: usize = 10;
@@ -46,6 +84,22 @@ pub const MAX_GREP_PER_FILE: usize = 10;
 /// 内容搜索总结果上限
 pub const MAX_GREP_TOTAL: usize = 200;
 
+#[allow(dead_code)]
+/// `--exec-batch` 单条命令携带的路径数上限，超出后分批执行（避免命令行过长）
+pub const EXEC_BATCH_SIZE: usize = 500;
+
+#[allow(dead_code)]
+/// 单个 PDF 文本提取的时间预算（毫秒），超时跳过该文件
+pub const PDF_EXTRACT_BUDGET_MS: u64 = 3_000;
+
+#[allow(dead_code)]
+/// 同时运行的 PDF 解析线程上限（超时的线程要等它自行结束才释放名额）
+pub const MAX_PDF_EXTRACT_THREADS: usize = 4;
+
+#[allow(dead_code)]
+/// 建立压缩包成员索引时 tar/tar.gz 的最大体积（需整体顺序读取）
+pub const MAX_ARCHIVE_SCAN_SIZE: u64 = 512 * 1024 * 1024;
+
 #[allow(dead_code)]
 /// 数据保存目录
 pub fn data_dir() -> PathBuf {
@@ -69,6 +123,32 @@ pub fn frecency_db_path() -> PathBuf {
     data_dir().join("frecency.json")
 }
 
+#[allow(dead_code)]
+/// 本地文件名索引缓存
+pub fn local_index_db_path() -> PathBuf {
+    let dir = dirs::cache_dir().unwrap_or_default();
+    std::fs::create_dir_all(&dir).ok();
+    dir.join("starsearch_local.redb")
+}
+
+#[allow(dead_code)]
+/// 全文索引数据库
+pub fn fulltext_db_path() -> PathBuf {
+    data_dir().join("fulltext.redb")
+}
+
+#[allow(dead_code)]
+/// 图片缩略图缓存目录
+pub fn thumbnail_cache_dir() -> PathBuf {
+    data_dir().join("thumbnails")
+}
+
+#[allow(dead_code)]
+/// 替换前的原文件备份目录
+pub fn backup_dir() -> PathBuf {
+    data_dir().join("backups")
+}
+
 #[allow(dead_code)]
 /// 二进制文件扩展名（跳过预览）
 pub const BINARY_EXTENSIONS: &[&str] = &[
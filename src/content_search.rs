@@ -1,11 +1,22 @@
 use anyhow::Result;
-use grep_regex::RegexMatcher;
-use grep_searcher::{sinks::UTF8, Searcher};
+use grep_matcher::Matcher;
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
+use grep_searcher::{BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkContextKind, SinkMatch};
 use rayon::prelude::*;
 use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::config::{GLOBAL_CONFIG, RuntimeConfig};
+use crate::config::{BINARY_EXTENSIONS, GLOBAL_CONFIG, MAX_GREP_DOCUMENT_SIZE, MAX_GREP_FILE_SIZE, MAX_GREP_PER_FILE, MAX_GREP_TOTAL, RuntimeConfig};
+use crate::encoding;
+use crate::extract;
+use crate::fulltext::{self, FullTextIndex};
+use crate::ranking::{self, ScoreBreakdown};
+use crate::types::{ContentLine, FileEntry};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ContentMatch {
@@ -13,15 +24,453 @@ pub struct ContentMatch {
     pub line_number: u64,
     pub line_content: String,
     pub score: f32, // 匹配度
+    /// 匹配度明细，仅在开启调试打分时输出
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_detail: Option<ScoreBreakdown>,
+    /// 文档内位置（如"段落 3"、"工作表 Sheet1 第 5 行"、"幻灯片 2"），纯文本文件为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locator: Option<String>,
+    /// PDF 页码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// 文本文件探测到的编码（UTF-8/GBK/UTF-16LE 等），文档为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<&'static str>,
+    /// 行内每处匹配的位置，相对 `line_content`
+    pub offsets: Vec<MatchOffset>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub context_before: Vec<ContextLine>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub context_after: Vec<ContextLine>,
 }
 
-pub struct ContentSearcher;
+/// 单处匹配在 `line_content` 中的位置
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MatchOffset {
+    /// 字节偏移，左闭右开
+    pub start: usize,
+    pub end: usize,
+    /// 字符列号，从 1 开始
+    pub column: usize,
+}
+
+/// 匹配行前后的上下文（保留缩进）
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextLine {
+    pub line_number: u64,
+    pub content: String,
+}
+
+/// 同一文件的全部匹配
+#[derive(Debug, Clone, Serialize)]
+pub struct FileMatches {
+    pub full_path: String,
+    /// 文件内最高匹配度
+    pub score: f32,
+    pub matches: Vec<ContentMatch>,
+}
+
+/// 文件未被搜索的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// 超过 `MAX_GREP_FILE_SIZE`（文档为 `MAX_GREP_DOCUMENT_SIZE`）
+    TooLarge,
+    /// 扩展名属于 `BINARY_EXTENSIONS`
+    BinaryExtension,
+    /// 文件头含 NUL 字节
+    BinaryContent,
+    /// 被扩展名包含/排除规则过滤
+    ExtensionFilter,
+    /// 文档加密且无法用空密码打开
+    Encrypted,
+    /// PDF 没有文本层（扫描件）
+    NoTextLayer,
+    /// 超出单文件提取时间预算
+    Timeout,
+    /// 文档损坏或格式不支持
+    ExtractFailed,
+    /// 替换：文档类文件只能搜索，不能改写
+    ReadOnlyFormat,
+    /// 替换：按探测到的编码无法无损解码或编码
+    Undecodable,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            SkipReason::TooLarge => "文件过大",
+            SkipReason::BinaryExtension => "二进制文件类型",
+            SkipReason::BinaryContent => "二进制内容",
+            SkipReason::ExtensionFilter => "扩展名不在搜索范围内",
+            SkipReason::Encrypted => "文档已加密",
+            SkipReason::NoTextLayer => "无文本层（可能是扫描件）",
+            SkipReason::Timeout => "文本提取超时",
+            SkipReason::ExtractFailed => "文本提取失败",
+            SkipReason::ReadOnlyFormat => "文档格式不支持替换",
+            SkipReason::Undecodable => "编码无法无损转换",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for SkipReason {}
+
+impl SkipReason {
+    /// 二进制和被过滤的文件数量巨大，只计数不逐个列出
+    fn is_listed(self) -> bool {
+        !matches!(self, SkipReason::BinaryExtension | SkipReason::BinaryContent | SkipReason::ExtensionFilter)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: SkipReason,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContentSearchResult {
+    /// 按匹配度排序的匹配行；按文件分组时为空
+    pub matches: Vec<ContentMatch>,
+    /// 按文件分组的结果（`group_by_file` 开启时）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileMatches>,
+    /// 未搜索的文件明细（二进制与被过滤文件只计数）
+    pub skipped: Vec<SkippedFile>,
+    /// 各原因跳过的文件数
+    pub skip_counts: BTreeMap<SkipReason, usize>,
+    /// 是否通过全文索引筛选了候选文件
+    pub used_index: bool,
+    /// 被调用方取消（结果不完整）
+    pub cancelled: bool,
+    /// 超出时间预算提前结束（结果不完整）
+    pub timed_out: bool,
+}
+
+#[derive(Default)]
+struct SkipLog {
+    files: Vec<SkippedFile>,
+    counts: BTreeMap<SkipReason, usize>,
+}
+
+impl SkipLog {
+    fn record(&mut self, path: &str, reason: SkipReason, message: String) {
+        *self.counts.entry(reason).or_default() += 1;
+        if reason.is_listed() {
+            self.files.push(SkippedFile { path: path.to_string(), reason, message });
+        }
+    }
+}
+
+/// 查询不是合法的正则表达式
+#[derive(Debug, Clone, Serialize)]
+pub struct InvalidPattern {
+    pub pattern: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for InvalidPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "无效的正则表达式 {:?}: {}", self.pattern, self.message)
+    }
+}
+
+impl std::error::Error for InvalidPattern {}
+
+/// 大小写匹配方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaseMode {
+    #[default]
+    Sensitive,
+    Insensitive,
+    /// 查询全为小写时忽略大小写，含大写字母时区分
+    Smart,
+}
+
+/// 内容搜索选项
+#[derive(Debug, Clone)]
+pub struct ContentSearchOptions {
+    /// 匹配行之前的上下文行数
+    pub context_before: usize,
+    /// 匹配行之后的上下文行数
+    pub context_after: usize,
+    /// 同一文件的匹配合并为一条文件记录
+    pub group_by_file: bool,
+    /// 纯文本文件大小上限
+    pub max_file_size: u64,
+    /// Office/ODF/PDF 文档大小上限
+    pub max_document_size: u64,
+    /// 单文件最多返回的匹配数
+    pub max_per_file: usize,
+    /// 整次搜索最多收集的匹配数（`RuntimeConfig::max_results` 也不能超过它）
+    pub max_total: usize,
+    /// 只搜索这些扩展名（小写、不含点），为空表示不限
+    pub include_extensions: Vec<String>,
+    /// 不搜索这些扩展名
+    pub exclude_extensions: Vec<String>,
+    /// 字面量查询先查全文索引（需事先建立），正则查询仍全量搜索；
+    /// 索引不存在、为空或答不了该查询时也全量搜索。上次更新后改动的文件不在索引中
+    pub use_index: bool,
+    /// 整次搜索的时间预算，超时返回已找到的结果
+    pub time_budget: Option<Duration>,
+    /// 结果附带匹配度明细（BM25、文件名/路径加成、时间加成）
+    pub score_detail: bool,
+    /// 查询按普通字符串匹配，不解析正则元字符
+    pub fixed_strings: bool,
+    pub case: CaseMode,
+    /// 只匹配完整单词
+    pub whole_word: bool,
+    /// 允许匹配跨行（查询可包含 `\n`）
+    pub multiline: bool,
+}
+
+impl Default for ContentSearchOptions {
+    fn default() -> Self {
+        Self {
+            context_before: 0,
+            context_after: 0,
+            group_by_file: false,
+            max_file_size: MAX_GREP_FILE_SIZE,
+            max_document_size: MAX_GREP_DOCUMENT_SIZE,
+            max_per_file: MAX_GREP_PER_FILE,
+            max_total: MAX_GREP_TOTAL,
+            include_extensions: Vec::new(),
+            exclude_extensions: Vec::new(),
+            use_index: false,
+            time_budget: None,
+            score_detail: false,
+            fixed_strings: false,
+            case: CaseMode::Sensitive,
+            whole_word: false,
+            multiline: false,
+        }
+    }
+}
+
+fn normalize_extensions(exts: &[String]) -> Vec<String> {
+    exts.iter()
+        .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+#[derive(Default, Clone)]
+pub struct ContentSearcher {
+    options: ContentSearchOptions,
+}
 
 impl ContentSearcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_context(mut self, before: usize, after: usize) -> Self {
+        self.options.context_before = before;
+        self.options.context_after = after;
+        self
+    }
+
+    pub fn with_grouping(mut self, enabled: bool) -> Self {
+        self.options.group_by_file = enabled;
+        self
+    }
+
+    pub fn with_extensions(mut self, include: &[String], exclude: &[String]) -> Self {
+        self.options.include_extensions = normalize_extensions(include);
+        self.options.exclude_extensions = normalize_extensions(exclude);
+        self
+    }
+
+    pub fn with_index(mut self, enabled: bool) -> Self {
+        self.options.use_index = enabled;
+        self
+    }
+
+    pub fn with_time_budget(mut self, budget: Option<Duration>) -> Self {
+        self.options.time_budget = budget;
+        self
+    }
+
+    pub fn with_fixed_strings(mut self, enabled: bool) -> Self {
+        self.options.fixed_strings = enabled;
+        self
+    }
+
+    pub fn with_case(mut self, case: CaseMode) -> Self {
+        self.options.case = case;
+        self
+    }
+
+    pub fn with_whole_word(mut self, enabled: bool) -> Self {
+        self.options.whole_word = enabled;
+        self
+    }
+
+    pub fn with_multiline(mut self, enabled: bool) -> Self {
+        self.options.multiline = enabled;
+        self
+    }
+
+    /// 按选项构建匹配器；正则语法错误返回 `InvalidPattern`，便于调用方原样报告
+    pub fn build_matcher(&self, query: &str) -> std::result::Result<RegexMatcher, InvalidPattern> {
+        let mut builder = RegexMatcherBuilder::new();
+        builder
+            .fixed_strings(self.options.fixed_strings)
+            .word(self.options.whole_word)
+            .case_insensitive(self.options.case == CaseMode::Insensitive)
+            .case_smart(self.options.case == CaseMode::Smart)
+            // 与 rg 相同：^/$ 始终按行匹配，替换时对整个文件匹配也不例外；`.` 不跨行
+            .multi_line(true);
+        if !self.options.multiline {
+            builder.line_terminator(Some(b'\n'));
+        }
+        builder.build(query).map_err(|e| InvalidPattern {
+            pattern: query.to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    pub fn with_score_detail(mut self, enabled: bool) -> Self {
+        self.options.score_detail = enabled;
+        self
+    }
+
+    pub fn with_max_per_file(mut self, max: usize) -> Self {
+        self.options.max_per_file = max;
+        self
+    }
+
+    pub fn with_max_total(mut self, max: usize) -> Self {
+        self.options.max_total = max;
+        self
+    }
+
+    /// 按扩展名、大小和文件头判断是否跳过；返回探测到的编码
+    fn check_file(&self, path: &Path, is_document: bool) -> std::result::Result<&'static encoding_rs::Encoding, (SkipReason, String)> {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        if (!self.options.include_extensions.is_empty() && !self.options.include_extensions.contains(&ext))
+            || self.options.exclude_extensions.contains(&ext)
+        {
+            return Err((SkipReason::ExtensionFilter, format!("扩展名 .{}", ext)));
+        }
+        if !is_document && BINARY_EXTENSIONS.contains(&ext.as_str()) {
+            return Err((SkipReason::BinaryExtension, format!("扩展名 .{}", ext)));
+        }
+
+        let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let limit = if is_document { self.options.max_document_size } else { self.options.max_file_size };
+        if size > limit {
+            return Err((SkipReason::TooLarge, format!("{} 字节，上限 {} 字节", size, limit)));
+        }
+        if is_document {
+            return Ok(encoding_rs::UTF_8);
+        }
+
+        match encoding::sniff_file(path) {
+            Ok((_, true)) => Err((SkipReason::BinaryContent, "文件头含 NUL 字节".to_string())),
+            Ok((file_encoding, false)) => Ok(file_encoding),
+            Err(_) => Ok(encoding_rs::UTF_8),
+        }
+    }
+
     // 统一内容搜索入口：本机/自定义路径通用
-    pub fn search(&self, query: &str, rt_config: &RuntimeConfig) -> Result<Vec<ContentMatch>> {
-        let matcher = RegexMatcher::new_line_matcher(query)?;
-        let results = Arc::new(Mutex::new(Vec::new()));
+    pub fn search(&self, query: &str, rt_config: &RuntimeConfig) -> Result<ContentSearchResult> {
+        let mut result = self.run(query, rt_config, None, None, CancelToken::new())?;
+
+        // 全部结果到齐后按 BM25 重新打分，再按匹配度排序
+        let mut final_results = std::mem::take(&mut result.matches);
+        ranking::rank(&mut final_results, query, self.options.score_detail);
+        final_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+
+        if self.options.group_by_file {
+            result.files = Self::group_by_file(final_results);
+        } else {
+            result.matches = final_results;
+        }
+        Ok(result)
+    }
+
+    /// 流式搜索：每条匹配（连同后文上下文）一经确定即发送到 `sender`，不排序也不分组，
+    /// 匹配度只是行内覆盖率（跨文件的 BM25 需要全部结果）；
+    /// 返回值只包含跳过统计等汇总信息。接收端关闭视同取消
+    pub fn search_stream(
+        &self,
+        query: &str,
+        rt_config: &RuntimeConfig,
+        sender: Sender<ContentMatch>,
+        cancel: CancelToken,
+    ) -> Result<ContentSearchResult> {
+        self.run(query, rt_config, None, Some(sender), cancel)
+    }
+
+    /// 列出范围内所有含匹配的文件：每个文件只取一条匹配，不受结果总数上限，
+    /// 供批量替换确定要改写的文件
+    pub fn matching_files(&self, query: &str, rt_config: &RuntimeConfig) -> Result<ContentSearchResult> {
+        let searcher = self.clone().with_context(0, 0).with_grouping(false).with_max_per_file(1).with_max_total(usize::MAX);
+        let limits = RuntimeConfig { max_results: usize::MAX, ..rt_config.clone() };
+        searcher.run(query, &limits, None, None, CancelToken::new())
+    }
+
+    /// 组合查询：只在文件名部分筛出的候选条目中匹配内容，返回附带匹配行的条目（按最高匹配度排序）；
+    /// `candidates` 为空表示没有文件名部分，按常规范围搜索后再生成条目
+    pub fn search_entries(&self, query: &str, candidates: Option<Vec<FileEntry>>, rt_config: &RuntimeConfig) -> Result<Vec<FileEntry>> {
+        let mut by_path: HashMap<String, FileEntry> = HashMap::new();
+        let files = candidates.map(|entries| {
+            entries
+                .into_iter()
+                .filter(|e| !e.is_dir)
+                .map(|e| {
+                    let path = e.path.clone();
+                    by_path.insert(path.clone(), e);
+                    path
+                })
+                .collect::<Vec<_>>()
+        });
+
+        // 候选文件的匹配数按总量上限统计，条目数再按 max_results 截断
+        let limits = RuntimeConfig { max_results: self.options.max_total, ..rt_config.clone() };
+        let mut result = self.run(query, &limits, files, None, CancelToken::new())?;
+        ranking::rank(&mut result.matches, query, self.options.score_detail);
+
+        let mut entries: Vec<FileEntry> = Self::group_by_file(result.matches)
+            .into_iter()
+            .map(|file| {
+                let mut entry = by_path.remove(&file.full_path).unwrap_or_else(|| entry_from_path(&file.full_path));
+                entry.score = file.matches.iter().map(|m| m.score).fold(0.0, f32::max);
+                entry.matches = file
+                    .matches
+                    .into_iter()
+                    .map(|m| ContentLine { line_number: m.line_number, line_content: m.line_content, locator: m.locator })
+                    .collect();
+                entry
+            })
+            .collect();
+        entries.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        entries.truncate(rt_config.max_results);
+        Ok(entries)
+    }
+
+    fn run(
+        &self,
+        query: &str,
+        rt_config: &RuntimeConfig,
+        files: Option<Vec<String>>,
+        sender: Option<Sender<ContentMatch>>,
+        cancel: CancelToken,
+    ) -> Result<ContentSearchResult> {
+        let matcher = self.build_matcher(query)?;
+        let out = Collector {
+            matches: Mutex::new(Vec::new()),
+            sender,
+            count: AtomicUsize::new(0),
+            max_results: rt_config.max_results.min(self.options.max_total),
+            cancel,
+            deadline: self.options.time_budget.map(|d| Instant::now() + d),
+            timed_out: AtomicBool::new(false),
+        };
+        let skipped = Mutex::new(SkipLog::default());
 
         // 确定搜索范围
         let search_paths = if rt_config.search_scope.is_empty() {
@@ -32,72 +481,597 @@ impl ContentSearcher {
             vec![rt_config.search_scope.clone()]
         };
 
-        // 多线程搜索
-        search_paths.par_iter().for_each(|path| {
-            let walker = ignore::WalkBuilder::new(path)
-                .git_ignore(true)
-                .hidden(false)
-                .follow_links(false)
-                .build();
-
-            for entry_result in walker {
-                let entry = match entry_result {
-                    Ok(e) => e,
-                    Err(_) => continue,
-                };
-
-                if !entry.file_type().is_some_and(|ft| ft.is_file()) {
-                    continue;
+        // 调用方指定了候选文件时直接使用；否则字面量查询先查全文索引，只对候选文件 grep 验证
+        let mut used_index = false;
+        let candidates = if files.is_some() {
+            files
+        } else if self.options.use_index && (self.options.fixed_strings || fulltext::is_literal(query)) {
+            let lookup = || -> Result<Option<Vec<String>>> {
+                match FullTextIndex::open_covering(&search_paths)? {
+                    Some(index) => index.candidates(query, self.options.whole_word),
+                    None => Ok(None),
+                }
+            };
+            match lookup() {
+                Ok(c) => {
+                    used_index = c.is_some();
+                    c
+                }
+                Err(e) => {
+                    tracing::warn!("全文索引不可用，改为全量搜索: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Some(candidates) = candidates {
+            candidates
+                .par_iter()
+                .filter(|p| search_paths.iter().any(|root| Path::new(p).starts_with(root)))
+                .for_each(|p| {
+                    if !out.should_stop() {
+                        self.search_file(&matcher, Path::new(p), &out, &skipped);
+                    }
+                });
+        } else {
+            // 多线程搜索
+            search_paths.par_iter().for_each(|path| {
+                let walker = ignore::WalkBuilder::new(path)
+                    .git_ignore(true)
+                    .hidden(false)
+                    .follow_links(false)
+                    .build();
+
+                for entry_result in walker {
+                    if out.should_stop() {
+                        break;
+                    }
+                    let entry = match entry_result {
+                        Ok(e) => e,
+                        Err(_) => continue,
+                    };
+
+                    if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+                        continue;
+                    }
+
+                    self.search_file(&matcher, entry.path(), &out, &skipped);
+                }
+            });
+        }
+
+        let skipped = skipped.into_inner().unwrap();
+        Ok(ContentSearchResult {
+            matches: out.matches.into_inner().unwrap(),
+            files: Vec::new(),
+            skipped: skipped.files,
+            skip_counts: skipped.counts,
+            used_index,
+            cancelled: out.cancel.is_cancelled(),
+            timed_out: out.timed_out.load(Ordering::Relaxed),
+        })
+    }
+
+    // 单个文件：过滤、按类型提取或转码后匹配
+    fn search_file(&self, matcher: &RegexMatcher, file_path: &Path, out: &Collector, skipped: &Mutex<SkipLog>) {
+        let path_str = file_path.to_string_lossy().to_string();
+        let is_document = extract::is_document(file_path);
+
+        let file_encoding = match self.check_file(file_path, is_document) {
+            Ok(e) => e,
+            Err((reason, message)) => {
+                skipped.lock().unwrap().record(&path_str, reason, message);
+                return;
+            }
+        };
+
+        // Office/ODF/PDF 文档先提取文本，再逐段匹配
+        if is_document {
+            if let Err(skip) = self.search_document(matcher, file_path, out) {
+                skipped.lock().unwrap().record(&skip.path, skip.reason, skip.message);
+            }
+            return;
+        }
+
+        // GBK/UTF-16 文件先转码为 UTF-8 再匹配
+        let mut builder = self.searcher_builder();
+        if file_encoding != encoding_rs::UTF_8 {
+            builder.encoding(grep_searcher::Encoding::new(file_encoding.name()).ok());
+        }
+        let mut searcher = builder.build();
+
+        // 搜索文件内容
+        let mut sink = MatchSink::new(matcher, out, self.options.max_per_file, ContentMatch {
+            encoding: Some(file_encoding.name()),
+            ..ContentMatch::empty(path_str.clone())
+        });
+        let _ = searcher.search_path(matcher, file_path, &mut sink);
+        sink.flush();
+        // 文件头之后才出现 NUL 的二进制文件，已找到的匹配保留
+        if let Some(offset) = sink.binary_offset {
+            skipped.lock().unwrap().record(&path_str, SkipReason::BinaryContent, format!("偏移 {} 处含 NUL 字节", offset));
+        }
+    }
+
+    fn searcher_builder(&self) -> SearcherBuilder {
+        let mut builder = SearcherBuilder::new();
+        builder
+            .line_number(true)
+            // 转码时去掉 BOM，首行的 `^` 和列号不受影响；UTF-8 文件也靠它识别 BOM
+            .bom_sniffing(true)
+            .multi_line(self.options.multiline)
+            .binary_detection(BinaryDetection::quit(b'\x00'))
+            .before_context(self.options.context_before)
+            .after_context(self.options.context_after);
+        builder
+    }
+
+    // 文档按提取出的文本块匹配：PDF 行号为页内行号，其他格式为块序号
+    fn search_document(&self, matcher: &RegexMatcher, file_path: &Path, out: &Collector) -> std::result::Result<(), SkippedFile> {
+        let path_str = file_path.to_string_lossy().to_string();
+        let blocks = extract::extract_text(file_path).map_err(|e| {
+            tracing::debug!("提取文档文本失败 {:?}: {}", file_path, e);
+            SkippedFile {
+                path: path_str.clone(),
+                reason: e.downcast_ref::<SkipReason>().copied().unwrap_or(SkipReason::ExtractFailed),
+                message: e.to_string(),
+            }
+        })?;
+        let mut searcher = self.searcher_builder().build();
+        // 整个文档共用一个 sink，单文件匹配上限跨块累计
+        let mut sink = MatchSink::new(matcher, out, self.options.max_per_file, ContentMatch::empty(path_str));
+
+        for (index, block) in blocks.iter().enumerate() {
+            sink.template.locator = Some(block.locator.clone());
+            sink.template.page = block.page;
+            sink.fixed_line = if block.page.is_none() { Some(index as u64 + 1) } else { None };
+            sink.before.clear();
+            let _ = searcher.search_slice(matcher, block.text.as_bytes(), &mut sink);
+            sink.flush();
+
+            if sink.file_matches >= self.options.max_per_file || out.should_stop() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // 按文件合并：文件顺序取其最高分匹配的位置，文件内按行号排列
+    fn group_by_file(matches: Vec<ContentMatch>) -> Vec<FileMatches> {
+        let mut files: Vec<FileMatches> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for m in matches {
+            match index.get(&m.full_path) {
+                Some(&i) => files[i].matches.push(m),
+                None => {
+                    index.insert(m.full_path.clone(), files.len());
+                    files.push(FileMatches { full_path: m.full_path.clone(), score: m.score, matches: vec![m] });
                 }
+            }
+        }
+        for file in &mut files {
+            file.matches.sort_by_key(|m| m.line_number);
+        }
+        files
+    }
+}
+
+impl ContentMatch {
+    fn empty(full_path: String) -> Self {
+        Self {
+            full_path,
+            line_number: 0,
+            line_content: String::new(),
+            score: 0.0,
+            score_detail: None,
+            locator: None,
+            page: None,
+            encoding: None,
+            offsets: Vec::new(),
+            context_before: Vec::new(),
+            context_after: Vec::new(),
+        }
+    }
+}
+
+// 无候选条目时按路径补全文件信息
+fn entry_from_path(full_path: &str) -> FileEntry {
+    let path = Path::new(full_path);
+    let metadata = std::fs::metadata(path).ok();
+    FileEntry {
+        name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        path: full_path.to_string(),
+        extension: path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase(),
+        size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+        modified: metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        drive: full_path.chars().next().filter(|_| full_path.get(1..2) == Some(":")).unwrap_or(' '),
+        ..Default::default()
+    }
+}
 
-                let file_path = entry.path();
-                let path_str = file_path.to_string_lossy().to_string();
-                let mut searcher = Searcher::new();
-
-                let results_clone = results.clone();
-                let query_str = query.to_string();
-                let max_results = rt_config.max_results;
-
-                // 搜索文件内容
-                let _ = searcher.search_path(
-                    &matcher,
-                    file_path,
-                    UTF8(|line_num, line| {
-                        let mut res = results_clone.lock().unwrap();
-                        if res.len() >= max_results {
-                            return Ok(false);
-                        }
-
-                        let line_str = line.trim().to_string();
-                        let score = Self::calc_match_score(&line_str, &query_str);
-
-                        res.push(ContentMatch {
-                            full_path: path_str.clone(),
-                            line_number: line_num,
-                            line_content: line_str,
-                            score,
-                        });
-                        Ok(true)
-                    }),
-                );
-                
-                if results.lock().unwrap().len() >= rt_config.max_results {
-                    break;
+/// 搜索取消令牌，可跨线程共享
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 匹配的去向：流式时发往通道，否则收集后统一排序
+struct Collector {
+    matches: Mutex<Vec<ContentMatch>>,
+    sender: Option<Sender<ContentMatch>>,
+    /// 已占用的结果名额（含尚未发出的匹配）
+    count: AtomicUsize,
+    max_results: usize,
+    cancel: CancelToken,
+    deadline: Option<Instant>,
+    timed_out: AtomicBool,
+}
+
+impl Collector {
+    /// 结果已满、已取消或超出时间预算
+    fn should_stop(&self) -> bool {
+        if self.cancel.is_cancelled() || self.count.load(Ordering::Relaxed) >= self.max_results {
+            return true;
+        }
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.timed_out.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    /// 申请一个结果名额
+    fn reserve(&self) -> bool {
+        self.count.fetch_add(1, Ordering::Relaxed) < self.max_results
+    }
+
+    fn emit(&self, m: ContentMatch) {
+        match &self.sender {
+            Some(tx) => {
+                if tx.send(m).is_err() {
+                    self.cancel.cancel();
                 }
             }
+            None => self.matches.lock().unwrap().push(m),
+        }
+    }
+}
+
+/// 收集匹配行及其上下文：前文在匹配到来前暂存；匹配先挂起，等后文收齐再发出
+struct MatchSink<'a> {
+    matcher: &'a RegexMatcher,
+    out: &'a Collector,
+    max_per_file: usize,
+    /// 本文件已收集的匹配数
+    file_matches: usize,
+    /// 检测到 NUL 字节时的偏移
+    binary_offset: Option<u64>,
+    /// 路径、编码、文档位置等每条匹配共有的字段
+    template: ContentMatch,
+    /// 非 PDF 文档以块序号代替行号
+    fixed_line: Option<u64>,
+    before: Vec<ContextLine>,
+    pending: Option<ContentMatch>,
+}
+
+impl<'a> MatchSink<'a> {
+    fn new(matcher: &'a RegexMatcher, out: &'a Collector, max_per_file: usize, template: ContentMatch) -> Self {
+        Self {
+            matcher,
+            out,
+            max_per_file,
+            file_matches: 0,
+            binary_offset: None,
+            template,
+            fixed_line: None,
+            before: Vec::new(),
+            pending: None,
+        }
+    }
+
+    /// 发出挂起的匹配
+    fn flush(&mut self) {
+        if let Some(m) = self.pending.take() {
+            self.out.emit(m);
+        }
+    }
+
+    /// 在去掉首尾空白后的行内定位每处匹配
+    fn offsets(&self, raw: &str, trimmed: &str) -> Vec<MatchOffset> {
+        let leading = raw.len() - raw.trim_start().len();
+        let mut offsets = Vec::new();
+        let _ = self.matcher.find_iter(raw.as_bytes(), |m| {
+            if m.is_empty() {
+                return true;
+            }
+            let start = m.start().saturating_sub(leading).min(trimmed.len());
+            let end = m.end().saturating_sub(leading).min(trimmed.len());
+            if start < end {
+                let column = trimmed.get(..start).map_or(start, |s| s.chars().count()) + 1;
+                offsets.push(MatchOffset { start, end, column });
+            }
+            true
         });
+        offsets
+    }
+}
 
-        let mut final_results = Arc::try_unwrap(results).unwrap().into_inner().unwrap();
-        // 按匹配度排序
-        final_results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-        Ok(final_results)
+fn line_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end_matches(['\r', '\n']).to_string()
+}
+
+impl Sink for MatchSink<'_> {
+    type Error = std::io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> std::result::Result<bool, Self::Error> {
+        self.flush();
+        // 达到单文件上限后仍会继续读取后文上下文，由下一次匹配在此终止
+        if self.file_matches >= self.max_per_file || self.out.should_stop() || !self.out.reserve() {
+            return Ok(false);
+        }
+
+        let raw = line_text(mat.bytes());
+        let line_str = raw.trim().to_string();
+        let offsets = self.offsets(&raw, &line_str);
+        let score = ranking::line_coverage(&line_str, &offsets);
+
+        self.pending = Some(ContentMatch {
+            line_number: self.fixed_line.or(mat.line_number()).unwrap_or(0),
+            line_content: line_str,
+            score,
+            offsets,
+            context_before: std::mem::take(&mut self.before),
+            ..self.template.clone()
+        });
+        self.file_matches += 1;
+        Ok(true)
+    }
+
+    fn context(&mut self, _searcher: &Searcher, ctx: &SinkContext<'_>) -> std::result::Result<bool, Self::Error> {
+        let line = ContextLine {
+            line_number: ctx.line_number().unwrap_or(0),
+            content: line_text(ctx.bytes()),
+        };
+        match ctx.kind() {
+            SinkContextKind::Before => self.before.push(line),
+            SinkContextKind::After => {
+                if let Some(m) = self.pending.as_mut() {
+                    m.context_after.push(line);
+                }
+            }
+            SinkContextKind::Other => {}
+        }
+        Ok(true)
+    }
+
+    fn binary_data(&mut self, _searcher: &Searcher, binary_byte_offset: u64) -> std::result::Result<bool, Self::Error> {
+        self.binary_offset = Some(binary_byte_offset);
+        Ok(false)
+    }
+
+    fn context_break(&mut self, _searcher: &Searcher) -> std::result::Result<bool, Self::Error> {
+        self.before.clear();
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grep_matcher::Matcher;
+
+    fn find_all(matcher: &RegexMatcher, text: &str) -> usize {
+        let mut count = 0;
+        matcher.find_iter(text.as_bytes(), |_| { count += 1; true }).unwrap();
+        count
+    }
+
+    #[test]
+    fn anchors_match_every_line() {
+        let text = "foo 1\nfoo 2\nbar foo\nfoo";
+        assert_eq!(find_all(&ContentSearcher::new().build_matcher("^foo").unwrap(), text), 3);
+        assert_eq!(find_all(&ContentSearcher::new().with_multiline(true).build_matcher("^foo").unwrap(), text), 3);
+        assert_eq!(find_all(&ContentSearcher::new().build_matcher(r"\d$").unwrap(), text), 2);
+    }
+
+    #[test]
+    fn invalid_regex_is_reported() {
+        let err = ContentSearcher::new().build_matcher("fn(").unwrap_err();
+        assert_eq!(err.pattern, "fn(");
+        assert!(ContentSearcher::new().with_fixed_strings(true).build_matcher("fn(").is_ok());
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("starsearch-content-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn scope(dir: &Path) -> RuntimeConfig {
+        RuntimeConfig {
+            search_scope: dir.to_string_lossy().to_string(),
+            is_content_search: true,
+            max_results: 1000,
+            include_archives: false,
+        }
+    }
+
+    #[test]
+    fn bom_does_not_shift_the_first_line() {
+        let dir = temp_dir("bom");
+        std::fs::write(dir.join("utf8.txt"), b"\xef\xbb\xbffoo bar\nfoo\n").unwrap();
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter().chain("foo bar\n".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        std::fs::write(dir.join("utf16.txt"), utf16).unwrap();
+
+        for multiline in [false, true] {
+            let result = ContentSearcher::new().with_multiline(multiline).search("^foo bar", &scope(&dir)).unwrap();
+            let mut hits: Vec<(String, u64, String, usize, usize)> = result
+                .matches
+                .iter()
+                .map(|m| {
+                    let name = Path::new(&m.full_path).file_name().unwrap().to_string_lossy().to_string();
+                    (name, m.line_number, m.line_content.clone(), m.offsets[0].start, m.offsets[0].column)
+                })
+                .collect();
+            hits.sort();
+            assert_eq!(hits, vec![
+                ("utf16.txt".to_string(), 1, "foo bar".to_string(), 0, 1),
+                ("utf8.txt".to_string(), 1, "foo bar".to_string(), 0, 1),
+            ]);
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    // (行号, 前文行号, 后文行号)
+    fn windows(matches: &[ContentMatch]) -> Vec<(u64, Vec<u64>, Vec<u64>)> {
+        let mut windows: Vec<_> = matches
+            .iter()
+            .map(|m| {
+                let numbers = |lines: &[ContextLine]| lines.iter().map(|l| l.line_number).collect::<Vec<_>>();
+                (m.line_number, numbers(&m.context_before), numbers(&m.context_after))
+            })
+            .collect();
+        windows.sort();
+        windows
+    }
+
+    #[test]
+    fn context_windows_do_not_repeat_lines() {
+        let dir = temp_dir("context");
+        std::fs::write(dir.join("a.txt"), "foo\na\nfoo\nb\nc\nfoo").unwrap();
+
+        // 首行、末行（无换行结尾）都能匹配，重叠的窗口里每行只出现一次
+        let result = ContentSearcher::new().with_context(1, 1).search("foo", &scope(&dir)).unwrap();
+        assert_eq!(windows(&result.matches), vec![(1, vec![], vec![2]), (3, vec![], vec![4]), (6, vec![5], vec![])]);
+        let m = result.matches.iter().find(|m| m.line_number == 6).unwrap();
+        assert_eq!(m.context_before[0].content, "c");
+
+        // 前文窗口跨过上一条匹配时不越过它
+        let result = ContentSearcher::new().with_context(3, 0).search("foo", &scope(&dir)).unwrap();
+        assert_eq!(windows(&result.matches), vec![(1, vec![], vec![]), (3, vec![2], vec![]), (6, vec![4, 5], vec![])]);
+        std::fs::remove_dir_all(&dir).ok();
     }
 
-    // 计算匹配度
-    fn calc_match_score(line: &str, query: &str) -> f32 {
-        let query_len = query.len() as f32;
-        let line_len = line.len() as f32;
-        let exact_match = if line.contains(query) { 1.0 } else { 0.5 };
-        exact_match * (query_len / line_len).min(1.0)
+    #[test]
+    fn grouping_keeps_line_order() {
+        let dir = temp_dir("group");
+        std::fs::write(dir.join("many.txt"), "x foo\nfoo\nlonger line with foo\nfoo foo\n").unwrap();
+        std::fs::write(dir.join("one.txt"), "padding line\nfoo in a long line of other words\n").unwrap();
+
+        let result = ContentSearcher::new().with_grouping(true).search("foo", &scope(&dir)).unwrap();
+        assert!(result.matches.is_empty());
+        assert_eq!(result.files.len(), 2);
+        assert!(result.files.windows(2).all(|w| w[0].score >= w[1].score));
+        for file in &result.files {
+            let lines: Vec<u64> = file.matches.iter().map(|m| m.line_number).collect();
+            assert!(lines.windows(2).all(|w| w[0] < w[1]), "{:?}", lines);
+            assert!(file.matches.iter().all(|m| m.full_path == file.full_path));
+            let best = file.matches.iter().map(|m| m.score).fold(f32::MIN, f32::max);
+            assert_eq!(file.score, best);
+        }
+        let many = result.files.iter().find(|f| f.full_path.ends_with("many.txt")).unwrap();
+        assert_eq!(many.matches.iter().map(|m| m.line_number).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    fn many_files(name: &str, count: usize) -> std::path::PathBuf {
+        let dir = temp_dir(name);
+        for i in 0..count {
+            std::fs::write(dir.join(format!("{}.txt", i)), "needle\n").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn stream_stops_when_cancelled() {
+        let dir = many_files("cancel", 3000);
+        let config = scope(&dir);
+        let config = RuntimeConfig { max_results: usize::MAX, ..config };
+
+        // 开始前已取消：一条都不发
+        let (tx, rx) = std::sync::mpsc::channel();
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let result = ContentSearcher::new().search_stream("needle", &config, tx, cancel).unwrap();
+        assert!(result.cancelled);
+        assert_eq!(rx.iter().count(), 0);
+
+        // 收到第一条后取消：剩下的文件不再搜索
+        let (tx, rx) = std::sync::mpsc::channel();
+        let cancel = CancelToken::new();
+        let worker = {
+            let cancel = cancel.clone();
+            let config = config.clone();
+            std::thread::spawn(move || ContentSearcher::new().with_max_total(usize::MAX).search_stream("needle", &config, tx, cancel))
+        };
+        rx.recv().unwrap();
+        cancel.cancel();
+        let result = worker.join().unwrap().unwrap();
+        assert!(result.cancelled && !result.timed_out);
+        assert!(rx.iter().count() + 1 < 3000);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn stream_stops_when_receiver_is_dropped() {
+        let dir = many_files("dropped", 200);
+        let (tx, rx) = std::sync::mpsc::channel();
+        drop(rx);
+        let result = ContentSearcher::new().search_stream("needle", &scope(&dir), tx, CancelToken::new()).unwrap();
+        // 第一次发送失败即视同取消
+        assert!(result.cancelled);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn stream_reports_exhausted_budgets() {
+        let dir = many_files("budget", 50);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let searcher = ContentSearcher::new().with_time_budget(Some(Duration::ZERO));
+        let result = searcher.search_stream("needle", &scope(&dir), tx, CancelToken::new()).unwrap();
+        assert!(result.timed_out && !result.cancelled);
+        assert_eq!(rx.iter().count(), 0);
+
+        // 结果数上限：恰好发出上限条数
+        let (tx, rx) = std::sync::mpsc::channel();
+        let config = RuntimeConfig { max_results: 7, ..scope(&dir) };
+        let result = ContentSearcher::new().search_stream("needle", &config, tx, CancelToken::new()).unwrap();
+        assert!(!result.timed_out && !result.cancelled);
+        assert_eq!(rx.iter().count(), 7);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn oversized_documents_are_skipped() {
+        let dir = std::env::temp_dir().join(format!("starsearch-content-docsize-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let doc = dir.join("big.docx");
+        std::fs::write(&doc, vec![0u8; 2048]).unwrap();
+
+        let mut searcher = ContentSearcher::new();
+        assert!(searcher.check_file(&doc, true).is_ok());
+        searcher.options.max_document_size = 1024;
+        assert_eq!(searcher.check_file(&doc, true).unwrap_err().0, SkipReason::TooLarge);
+        std::fs::remove_dir_all(&dir).ok();
     }
 }
@@ -2,12 +2,15 @@ use anyhow::Result;
 use walkdir::WalkDir;
 use std::time::UNIX_EPOCH;
 
+use crate::archive;
 use crate::config::RuntimeConfig;
+use crate::query::LocalQuery;
 use crate::types::FileEntry;
 
 // 自定义路径扫描（U盘/外挂盘，按需扫描）
 pub async fn search_custom_path(query: &str, rt_config: &RuntimeConfig) -> Result<Vec<FileEntry>> {
-    let query_lower = query.to_lowercase();
+    let query = LocalQuery::parse(query);
+    let query_lower = query.keyword.to_lowercase();
     let mut results = Vec::with_capacity(rt_config.max_results);
 
     // 仅扫描指定路径，不全盘
@@ -45,7 +48,7 @@ pub async fn search_custom_path(query: &str, rt_config: &RuntimeConfig) -> Resul
                 .unwrap_or("")
                 .to_lowercase();
 
-            results.push(FileEntry {
+            let entry = FileEntry {
                 name,
                 path,
                 extension,
@@ -54,9 +57,65 @@ pub async fn search_custom_path(query: &str, rt_config: &RuntimeConfig) -> Resul
                 size: metadata.len(),
                 drive: ' ',
                 score: 0.0,
-            });
+                ..Default::default()
+            };
+            if query.accepts(entry.kind) && query.matches_filters(&entry) {
+                results.push(entry);
+            }
+        }
+
+        // 压缩包成员：按虚拟路径匹配
+        if rt_config.include_archives && !metadata.is_dir() && archive::is_archive(entry_result.path()) {
+            for member in archive::member_entries(&entry_result.path().to_string_lossy(), ' ') {
+                if results.len() >= rt_config.max_results {
+                    break;
+                }
+                if (member.name.to_lowercase().contains(&query_lower)
+                    || member.path.to_lowercase().contains(&query_lower))
+                    && query.accepts(member.kind)
+                    && query.matches_filters(&member) {
+                    results.push(member);
+                }
+            }
         }
     }
 
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(scope: &std::path::Path) -> RuntimeConfig {
+        RuntimeConfig {
+            search_scope: scope.to_string_lossy().to_string(),
+            is_content_search: false,
+            max_results: 100,
+            include_archives: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn modifiers_filter_the_scan() {
+        let dir = std::env::temp_dir().join(format!("starsearch-custom-path-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/report.rs"), "").unwrap();
+        std::fs::write(dir.join("report.txt"), "").unwrap();
+
+        let names = |entries: Vec<FileEntry>| {
+            let mut names: Vec<String> = entries.into_iter().map(|e| e.name).collect();
+            names.sort();
+            names
+        };
+        let found = search_custom_path("report ext:rs", &config(&dir)).await.unwrap();
+        assert_eq!(names(found), vec!["report.rs"]);
+        let found = search_custom_path("report path:src", &config(&dir)).await.unwrap();
+        assert_eq!(names(found), vec!["report.rs"]);
+        // 目录扫描得不到已删除文件和数据流
+        assert!(search_custom_path("deleted: report", &config(&dir)).await.unwrap().is_empty());
+        assert!(search_custom_path("ads: report", &config(&dir)).await.unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,116 @@
+use encoding_rs::{Encoding, GBK, UTF_16BE, UTF_16LE, UTF_8};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// 编码探测读取的文件头大小
+const SNIFF_BYTES: usize = 8 * 1024;
+
+/// 探测文本编码：BOM 优先；其次按 NUL 分布识别无 BOM 的 UTF-16；最后在 UTF-8 与 GBK 之间选择
+pub fn detect(bytes: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    // 英文/数字为主的 UTF-16 文本，每两个字节中有一个是 0
+    let pairs = bytes.len() / 2;
+    if pairs >= 2 {
+        let even_zero = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+        let odd_zero = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+        if odd_zero * 10 >= pairs * 3 && even_zero * 10 < pairs {
+            return UTF_16LE;
+        }
+        if even_zero * 10 >= pairs * 3 && odd_zero * 10 < pairs {
+            return UTF_16BE;
+        }
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => UTF_8,
+        // 样本在多字节字符中间截断，不算非法
+        Err(e) if e.error_len().is_none() => UTF_8,
+        Err(_) => {
+            // 同样允许末尾截断半个双字节字符
+            let is_gbk = |b: &[u8]| GBK.decode_without_bom_handling_and_without_replacement(b).is_some();
+            if is_gbk(bytes) || is_gbk(&bytes[..bytes.len() - 1]) {
+                GBK
+            } else {
+                UTF_8
+            }
+        }
+    }
+}
+
+/// 读取文件头探测编码
+pub fn detect_file(path: &Path) -> std::io::Result<&'static Encoding> {
+    Ok(sniff_file(path)?.0)
+}
+
+/// 读取文件头，返回 (编码, 是否像二进制)；UTF-16 以外的文本不应含 NUL 字节
+pub fn sniff_file(path: &Path) -> std::io::Result<(&'static Encoding, bool)> {
+    let mut sample = Vec::with_capacity(SNIFF_BYTES);
+    File::open(path)?.take(SNIFF_BYTES as u64).read_to_end(&mut sample)?;
+    let encoding = detect(&sample);
+    let binary = encoding != UTF_16LE && encoding != UTF_16BE && sample.contains(&0);
+    Ok((encoding, binary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, le: bool) -> Vec<u8> {
+        text.encode_utf16().flat_map(|u| if le { u.to_le_bytes() } else { u.to_be_bytes() }).collect()
+    }
+
+    #[test]
+    fn bom_wins() {
+        assert_eq!(detect(b"\xef\xbb\xbfabc"), UTF_8);
+        assert_eq!(detect(b"\xff\xfea\0b\0"), UTF_16LE);
+        assert_eq!(detect(b"\xfe\xff\0a\0b"), UTF_16BE);
+        // BOM 后面是 GBK 字节也按 BOM 判断
+        assert_eq!(detect(b"\xef\xbb\xbf\xc4\xe3"), UTF_8);
+    }
+
+    #[test]
+    fn utf16_without_bom_is_recognized_by_nul_pattern() {
+        assert_eq!(detect(&utf16("hello world", true)), UTF_16LE);
+        assert_eq!(detect(&utf16("hello world", false)), UTF_16BE);
+        // 纯中文的 UTF-16 没有 NUL，识别不了，不能误判为 UTF-16
+        assert_ne!(detect(b"a\0"), UTF_16LE);
+        assert_eq!(detect(b"plain ascii"), UTF_8);
+    }
+
+    #[test]
+    fn gbk_versus_utf8() {
+        let (gbk, _, _) = GBK.encode("中文搜索");
+        assert_eq!(detect(&gbk), GBK);
+        assert_eq!(detect("中文搜索".as_bytes()), UTF_8);
+        // 既不是 UTF-8 也不是 GBK 的字节按 UTF-8 处理（替换字符）
+        assert_eq!(detect(b"\xff\xff\xff"), UTF_8);
+    }
+
+    #[test]
+    fn sample_cut_inside_a_character() {
+        let utf8 = "中文搜索".as_bytes();
+        assert_eq!(detect(&utf8[..utf8.len() - 1]), UTF_8);
+        let (gbk, _, _) = GBK.encode("中文搜索");
+        assert_eq!(detect(&gbk[..gbk.len() - 1]), GBK);
+    }
+
+    #[test]
+    fn sniff_file_flags_binary() {
+        let dir = std::env::temp_dir().join(format!("starsearch-encoding-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let text = dir.join("text.txt");
+        std::fs::write(&text, "中文".repeat(SNIFF_BYTES)).unwrap();
+        assert_eq!(sniff_file(&text).unwrap(), (UTF_8, false));
+        let wide = dir.join("wide.txt");
+        std::fs::write(&wide, utf16("hello", true)).unwrap();
+        assert_eq!(sniff_file(&wide).unwrap(), (UTF_16LE, false));
+        let binary = dir.join("bin.dat");
+        std::fs::write(&binary, b"MZ\x90\0\x03\0\0\0\x04\0\0\0\xff\xff\0\0\xb8\0\0\0\0\0\0\0").unwrap();
+        assert!(sniff_file(&binary).unwrap().1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,296 @@
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{bail, Context, Result};
+use rayon::prelude::*;
+
+/// 模板中的占位符（与 fd 一致，另加 `{ext}`）
+const PLACEHOLDERS: &[&str] = &["{}", "{/}", "{//}", "{.}", "{/.}", "{ext}"];
+
+/// 对搜索结果执行的命令模板，如 `code {}`、`zip out.zip {}`
+///
+/// 模板拆分参数后直接执行，不经过 shell，路径中的空格与引号不需要转义。
+/// 非 Windows 按 POSIX shell 规则拆分（`shell_words`）；Windows 上反斜杠是路径分隔符，
+/// 只按空白和引号拆分。占位符：
+/// - `{}` 完整路径
+/// - `{/}` 文件名
+/// - `{//}` 父目录
+/// - `{.}` 去掉扩展名的路径
+/// - `{/.}` 去掉扩展名的文件名
+/// - `{ext}` 扩展名（不含点）
+///
+/// 模板中没有占位符时，在末尾追加 `{}`
+#[derive(Debug, Clone)]
+pub struct CommandTemplate {
+    args: Vec<String>,
+}
+
+/// 执行汇总
+#[derive(Debug, Default, serde::Serialize)]
+pub struct ExecSummary {
+    /// 执行的命令数
+    pub commands: usize,
+    /// 退出码非 0 或无法启动的命令数
+    pub failed: usize,
+}
+
+impl CommandTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        #[cfg(windows)]
+        let split = split_plain(template);
+        #[cfg(not(windows))]
+        let split = shell_words::split(template).map_err(anyhow::Error::from);
+        let mut args = split.with_context(|| format!("无法解析命令模板: {}", template))?;
+        if args.is_empty() {
+            bail!("命令模板为空");
+        }
+        if !args.iter().any(|a| has_placeholder(a)) {
+            args.push("{}".to_string());
+        }
+        Ok(Self { args })
+    }
+
+    /// 为单个路径生成命令参数
+    pub fn expand(&self, path: &str) -> Vec<String> {
+        self.args.iter().map(|a| substitute(a, path)).collect()
+    }
+
+    /// 为一批路径生成一条命令：含占位符的参数按路径逐个展开
+    pub fn expand_batch(&self, paths: &[String]) -> Vec<String> {
+        let mut out = Vec::new();
+        for arg in &self.args {
+            if has_placeholder(arg) {
+                out.extend(paths.iter().map(|p| substitute(arg, p)));
+            } else {
+                out.push(arg.clone());
+            }
+        }
+        out
+    }
+
+    /// 每个路径执行一次，最多 `jobs` 个并行；各命令的输出整块写出，不会相互穿插
+    pub fn run_each(&self, paths: &[String], jobs: usize) -> Result<ExecSummary> {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+        let failed = AtomicUsize::new(0);
+        pool.install(|| {
+            paths.par_iter().for_each(|path| {
+                if !run_command(&self.expand(path), jobs > 1) {
+                    failed.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        });
+        Ok(ExecSummary { commands: paths.len(), failed: failed.into_inner() })
+    }
+
+    /// 所有路径合成一条命令执行；路径过多时按 `batch_size` 分批
+    pub fn run_batch(&self, paths: &[String], batch_size: usize) -> ExecSummary {
+        let mut summary = ExecSummary::default();
+        for chunk in paths.chunks(batch_size.max(1)) {
+            summary.commands += 1;
+            if !run_command(&self.expand_batch(chunk), false) {
+                summary.failed += 1;
+            }
+        }
+        summary
+    }
+}
+
+/// 按空白拆分参数，单双引号内的空白不拆分，引号本身去掉；反斜杠没有转义含义
+fn split_plain(template: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+    for c in template.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        bail!("缺少配对的引号");
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+/// 在 `path_var` 各目录中按 `pathext` 的扩展名查找程序，如 `code` -> `code.cmd`。
+/// 带路径或扩展名的程序名原样使用
+fn find_program(name: &str, path_var: &OsStr, pathext: &str) -> Option<PathBuf> {
+    if Path::new(name).extension().is_some() || name.contains(['/', '\\']) {
+        return None;
+    }
+    std::env::split_paths(path_var).find_map(|dir| {
+        pathext
+            .split(';')
+            .filter(|ext| !ext.is_empty())
+            .map(|ext| dir.join(format!("{}{}", name, ext.to_lowercase())))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+fn has_placeholder(arg: &str) -> bool {
+    PLACEHOLDERS.iter().any(|p| arg.contains(p))
+}
+
+/// 替换参数中的占位符；先匹配较长的占位符，避免 `{//}` 被当成 `{/}`
+fn substitute(arg: &str, path: &str) -> String {
+    let p = Path::new(path);
+    let lossy = |s: Option<&std::ffi::OsStr>| s.map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let name = lossy(p.file_name());
+    let parent = p
+        .parent()
+        .map(|d| d.to_string_lossy().to_string())
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| ".".to_string());
+    let ext = lossy(p.extension());
+    let stem = lossy(p.file_stem());
+    // 原样保留文件名之前的部分（分隔符不做规范化），只把文件名换成不带扩展名的 stem；
+    // 末尾的分隔符不属于文件名，`..` 等没有文件名的路径不变
+    let trimmed = path.trim_end_matches(std::path::is_separator);
+    let no_ext = match trimmed.strip_suffix(name.as_str()) {
+        Some(dir) if !name.is_empty() => format!("{}{}", dir, stem),
+        _ => path.to_string(),
+    };
+
+    let mut out = String::with_capacity(arg.len() + path.len());
+    let mut rest = arg;
+    'outer: while !rest.is_empty() {
+        for (token, value) in [("{//}", &parent), ("{/.}", &stem), ("{ext}", &ext), ("{/}", &name), ("{.}", &no_ext), ("{}", &path.to_string())] {
+            if let Some(after) = rest.strip_prefix(token) {
+                out.push_str(value);
+                rest = after;
+                continue 'outer;
+            }
+        }
+        let ch = rest.chars().next().unwrap();
+        out.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    out
+}
+
+/// 执行命令并把其输出转到本进程；返回是否成功。
+/// 并行时先收集输出再整块写出，串行时直接继承标准输出
+fn run_command(args: &[String], capture: bool) -> bool {
+    // Command 在 Windows 上只会补 .exe；`code` 这类 .cmd/.bat 启动器按 PATHEXT 找到完整路径，
+    // 标准库会为批处理文件调用 cmd.exe 并正确转义参数
+    let program: PathBuf = if cfg!(windows) {
+        let path_var = std::env::var_os("PATH").unwrap_or_default();
+        let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+        find_program(&args[0], &path_var, &pathext).unwrap_or_else(|| PathBuf::from(&args[0]))
+    } else {
+        PathBuf::from(&args[0])
+    };
+    let mut command = Command::new(program);
+    command.args(&args[1..]);
+    if !capture {
+        return match command.status() {
+            Ok(status) => status.success(),
+            Err(e) => {
+                eprintln!("无法执行 {}: {}", args[0], e);
+                false
+            }
+        };
+    }
+    match command.output() {
+        Ok(output) => {
+            // 下游管道关闭时忽略写入错误
+            let _ = std::io::stdout().lock().write_all(&output.stdout);
+            let _ = std::io::stderr().lock().write_all(&output.stderr);
+            output.status.success()
+        }
+        Err(e) => {
+            eprintln!("无法执行 {}: {}", args[0], e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_every_placeholder() {
+        let t = CommandTemplate::parse("echo {} {/} {//} {.} {/.} {ext}").unwrap();
+        assert_eq!(
+            t.expand("/tmp/a b/x.tar.gz"),
+            ["echo", "/tmp/a b/x.tar.gz", "x.tar.gz", "/tmp/a b", "/tmp/a b/x.tar", "x.tar", "gz"]
+        );
+        assert_eq!(substitute("{.}|{ext}|{//}", "noext"), "noext||.");
+        assert_eq!(substitute("{{}}", "p"), "{p}");
+    }
+
+    #[test]
+    fn no_ext_only_touches_the_file_name() {
+        assert_eq!(substitute("{.}", "/tmp/a.b/file"), "/tmp/a.b/file");
+        assert_eq!(substitute("{.}", "/tmp/a.b/file.txt"), "/tmp/a.b/file");
+        assert_eq!(substitute("{.}", "dir.d/"), "dir");
+        assert_eq!(substitute("{.}", "/x/archive.tar.gz/"), "/x/archive.tar");
+        assert_eq!(substitute("{.}", ".bashrc"), ".bashrc");
+        assert_eq!(substitute("{.}", "/tmp/.."), "/tmp/..");
+        assert_eq!(substitute("{.}|{/.}|{ext}", "./a.rs"), "./a|a|rs");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn no_ext_keeps_windows_separators() {
+        assert_eq!(substitute("{.}", r"C:\proj.v2\src\main.rs"), r"C:\proj.v2\src\main");
+        assert_eq!(substitute("{.}", r"C:\proj.v2\"), r"C:\proj");
+    }
+
+    #[test]
+    fn missing_placeholder_appends_path() {
+        assert_eq!(CommandTemplate::parse("code").unwrap().expand("a.rs"), ["code", "a.rs"]);
+        assert!(CommandTemplate::parse("  ").is_err());
+    }
+
+    #[test]
+    fn batch_repeats_placeholder_args() {
+        let t = CommandTemplate::parse("zip out.zip {} --name={/}").unwrap();
+        let paths = ["/a/1.txt".to_string(), "/b/2.txt".to_string()];
+        assert_eq!(
+            t.expand_batch(&paths),
+            ["zip", "out.zip", "/a/1.txt", "/b/2.txt", "--name=1.txt", "--name=2.txt"]
+        );
+    }
+
+    #[test]
+    fn plain_split_keeps_backslashes() {
+        assert_eq!(split_plain(r#"echo D:\backup "C:\Program Files\x" {}"#).unwrap(), ["echo", r"D:\backup", r"C:\Program Files\x", "{}"]);
+        assert_eq!(split_plain(r#"a "" 'b c'"#).unwrap(), ["a", "", "b c"]);
+        assert!(split_plain(r#"echo "open"#).is_err());
+    }
+
+    #[test]
+    fn finds_batch_launchers_via_pathext() {
+        let dir = std::env::temp_dir().join(format!("starsearch-exec-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("code.cmd"), "").unwrap();
+        let path_var = dir.clone().into_os_string();
+        assert_eq!(find_program("code", &path_var, ".COM;.EXE;.BAT;.CMD"), Some(dir.join("code.cmd")));
+        assert_eq!(find_program("code.cmd", &path_var, ".CMD"), None);
+        assert_eq!(find_program("missing", &path_var, ".CMD"), None);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
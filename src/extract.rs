@@ -0,0 +1,678 @@
+use anyhow::{Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::{MAX_DOCUMENT_PART_SIZE, MAX_PDF_EXTRACT_THREADS, PDF_EXTRACT_BUDGET_MS};
+use crate::content_search::SkipReason;
+
+/// 从文档中提取的一段文本，`locator` 描述其位置（段落/工作表行/幻灯片/页）
+#[derive(Debug, Clone)]
+pub struct TextBlock {
+    pub locator: String,
+    pub text: String,
+    /// PDF 页码（从 1 开始），其他格式为空
+    pub page: Option<u32>,
+}
+
+/// 可提取文本的文档扩展名（OOXML / ODF 本质都是 zip 包内的 XML）
+pub const DOCUMENT_EXTENSIONS: &[&str] = &["docx", "xlsx", "pptx", "odt", "ods", "odp", "pdf"];
+
+pub fn is_document(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| DOCUMENT_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// 提取文档纯文本，按段落/行/幻灯片切分
+pub fn extract_text(path: &Path) -> Result<Vec<TextBlock>> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    if ext == "pdf" {
+        return extract_pdf(path);
+    }
+    let mut zip = zip::ZipArchive::new(BufReader::new(File::open(path)?))?;
+
+    match ext.as_str() {
+        "docx" => Ok(extract_docx(&read_entry(&mut zip, "word/document.xml")?)),
+        "pptx" => extract_pptx(&mut zip),
+        "xlsx" => extract_xlsx(&mut zip),
+        "odt" | "ods" | "odp" => Ok(extract_odf(&read_entry(&mut zip, "content.xml")?)),
+        _ => anyhow::bail!("不支持的文档格式: {}", ext),
+    }
+}
+
+type Zip = zip::ZipArchive<BufReader<File>>;
+
+fn read_entry<R: Read + std::io::Seek>(zip: &mut zip::ZipArchive<R>, name: &str) -> Result<String> {
+    read_entry_limited(zip, name, MAX_DOCUMENT_PART_SIZE)
+}
+
+/// 读取包内部件，解压后超过 `limit` 字节视为损坏（声明的大小不可信，按实际读取量判断）
+fn read_entry_limited<R: Read + std::io::Seek>(zip: &mut zip::ZipArchive<R>, name: &str, limit: u64) -> Result<String> {
+    let entry = zip.by_name(name).with_context(|| format!("文档缺少 {}", name))?;
+    let mut xml = String::new();
+    entry.take(limit + 1).read_to_string(&mut xml)?;
+    if xml.len() as u64 > limit {
+        anyhow::bail!("{} 解压后超过 {} 字节", name, limit);
+    }
+    Ok(xml)
+}
+
+fn attr(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name)
+        .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+}
+
+/// 收集所有 `<p>` 段落的文本（docx 的 `w:p`、pptx 的 `a:p`），文本来自 `<t>`
+fn collect_paragraphs(xml: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+    let mut in_text = false;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"p" => current.clear(),
+                b"t" => in_text = true,
+                b"tab" => current.push('\t'),
+                _ => {}
+            },
+            Ok(Event::Empty(e)) => match e.local_name().as_ref() {
+                b"tab" => current.push('\t'),
+                b"br" => current.push(' '),
+                b"p" => paragraphs.push(String::new()),
+                _ => {}
+            },
+            Ok(Event::Text(t)) if in_text => {
+                if let Ok(text) = t.unescape() {
+                    current.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"p" => paragraphs.push(std::mem::take(&mut current)),
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    paragraphs
+}
+
+fn extract_docx(xml: &str) -> Vec<TextBlock> {
+    collect_paragraphs(xml)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, p)| !p.trim().is_empty())
+        .map(|(i, text)| TextBlock { locator: format!("段落 {}", i + 1), text, page: None })
+        .collect()
+}
+
+fn extract_pptx<R: Read + std::io::Seek>(zip: &mut zip::ZipArchive<R>) -> Result<Vec<TextBlock>> {
+    // 按幻灯片编号排序（slide10 排在 slide9 之后）
+    let mut slides: Vec<(u32, String)> = zip
+        .file_names()
+        .filter_map(|name| {
+            let n = name.strip_prefix("ppt/slides/slide")?.strip_suffix(".xml")?;
+            Some((n.parse().ok()?, name.to_string()))
+        })
+        .collect();
+    slides.sort();
+
+    let mut blocks = Vec::new();
+    for (number, name) in slides {
+        let xml = read_entry(zip, &name)?;
+        blocks.extend(
+            collect_paragraphs(&xml)
+                .into_iter()
+                .filter(|p| !p.trim().is_empty())
+                .map(|text| TextBlock { locator: format!("幻灯片 {}", number), text, page: None }),
+        );
+    }
+    Ok(blocks)
+}
+
+fn extract_xlsx(zip: &mut Zip) -> Result<Vec<TextBlock>> {
+    let shared = match read_entry(zip, "xl/sharedStrings.xml") {
+        Ok(xml) => parse_shared_strings(&xml),
+        Err(_) => Vec::new(),
+    };
+
+    // 工作表名 -> 文件路径：workbook.xml 给出 r:id，再由 rels 映射到 worksheets/sheetN.xml
+    let rels: HashMap<String, String> = {
+        let xml = read_entry(zip, "xl/_rels/workbook.xml.rels")?;
+        let mut reader = Reader::from_str(&xml);
+        let mut rels = HashMap::new();
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) | Ok(Event::Empty(e)) if e.local_name().as_ref() == b"Relationship" => {
+                    if let (Some(id), Some(target)) = (attr(&e, b"Id"), attr(&e, b"Target")) {
+                        let target = target.trim_start_matches('/');
+                        let target = target.strip_prefix("xl/").unwrap_or(target);
+                        rels.insert(id, format!("xl/{}", target));
+                    }
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+        }
+        rels
+    };
+
+    let sheets: Vec<(String, String)> = {
+        let xml = read_entry(zip, "xl/workbook.xml")?;
+        let mut reader = Reader::from_str(&xml);
+        let mut sheets = Vec::new();
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) | Ok(Event::Empty(e)) if e.local_name().as_ref() == b"sheet" => {
+                    let name = attr(&e, b"name").unwrap_or_default();
+                    if let Some(target) = attr(&e, b"r:id").and_then(|id| rels.get(&id)) {
+                        sheets.push((name, target.clone()));
+                    }
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+        }
+        sheets
+    };
+
+    let mut blocks = Vec::new();
+    for (sheet_name, target) in sheets {
+        let xml = match read_entry(zip, &target) {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+        blocks.extend(parse_sheet_rows(&xml, &shared).into_iter().map(|(row, text)| TextBlock {
+            locator: format!("工作表 {} 第 {} 行", sheet_name, row),
+            text,
+            page: None,
+        }));
+    }
+    Ok(blocks)
+}
+
+fn parse_shared_strings(xml: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    let mut in_text = false;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"si" => current.clear(),
+                b"t" => in_text = true,
+                _ => {}
+            },
+            Ok(Event::Text(t)) if in_text => {
+                if let Ok(text) = t.unescape() {
+                    current.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"si" => strings.push(std::mem::take(&mut current)),
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    strings
+}
+
+/// 解析工作表，每行返回 (行号, 以制表符连接的单元格文本)
+fn parse_sheet_rows(xml: &str, shared: &[String]) -> Vec<(u64, String)> {
+    let mut reader = Reader::from_str(xml);
+    let mut rows = Vec::new();
+    let mut row_number = 0u64;
+    let mut cells: Vec<String> = Vec::new();
+    let mut cell_type = String::new();
+    let mut value = String::new();
+    let mut in_value = false;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"row" => {
+                    row_number = attr(&e, b"r").and_then(|r| r.parse().ok()).unwrap_or(row_number + 1);
+                    cells.clear();
+                }
+                b"c" => {
+                    cell_type = attr(&e, b"t").unwrap_or_default();
+                    value.clear();
+                }
+                b"v" | b"t" => in_value = true,
+                _ => {}
+            },
+            Ok(Event::Text(t)) if in_value => {
+                if let Ok(text) = t.unescape() {
+                    value.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"v" | b"t" => in_value = false,
+                b"c" => {
+                    let text = if cell_type == "s" {
+                        value.trim().parse::<usize>().ok().and_then(|i| shared.get(i)).cloned().unwrap_or_default()
+                    } else {
+                        std::mem::take(&mut value)
+                    };
+                    if !text.is_empty() {
+                        cells.push(text);
+                    }
+                }
+                b"row" if !cells.is_empty() => rows.push((row_number, cells.join("\t"))),
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    rows
+}
+
+/// ODF 的 content.xml：文本文档按段落，表格按工作表行，演示文稿按幻灯片
+fn extract_odf(xml: &str) -> Vec<TextBlock> {
+    let mut reader = Reader::from_str(xml);
+    let mut blocks = Vec::new();
+    let mut paragraph = String::new();
+    let mut paragraph_depth = 0usize;
+    let mut paragraph_count = 0usize;
+
+    let mut table_name: Option<String> = None;
+    let mut row_number = 0u64;
+    let mut row_repeat = 1u64;
+    let mut row_cells: Vec<String> = Vec::new();
+    let mut cell_text = String::new();
+    let mut in_cell = false;
+
+    let mut slide: Option<usize> = None;
+    let mut slide_count = 0usize;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.name().as_ref() {
+                b"text:p" | b"text:h" => {
+                    if paragraph_depth == 0 {
+                        paragraph.clear();
+                    }
+                    paragraph_depth += 1;
+                }
+                b"text:tab" => paragraph.push('\t'),
+                b"table:table" => {
+                    table_name = attr(&e, b"table:name");
+                    row_number = 0;
+                }
+                b"table:table-row" => {
+                    row_repeat = attr(&e, b"table:number-rows-repeated").and_then(|r| r.parse().ok()).unwrap_or(1);
+                    row_cells.clear();
+                }
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    in_cell = true;
+                    cell_text.clear();
+                }
+                b"draw:page" => {
+                    slide_count += 1;
+                    slide = Some(slide_count);
+                }
+                _ => {}
+            },
+            Ok(Event::Empty(e)) => match e.name().as_ref() {
+                b"text:s" => paragraph.push(' '),
+                b"text:tab" => paragraph.push('\t'),
+                b"text:line-break" => paragraph.push(' '),
+                b"text:p" | b"text:h" if table_name.is_none() => paragraph_count += 1,
+                b"table:table-row" => {
+                    row_number += attr(&e, b"table:number-rows-repeated").and_then(|r| r.parse().ok()).unwrap_or(1);
+                }
+                _ => {}
+            },
+            Ok(Event::Text(t)) if paragraph_depth > 0 => {
+                if let Ok(text) = t.unescape() {
+                    paragraph.push_str(&text);
+                }
+            }
+            Ok(Event::End(e)) => match e.name().as_ref() {
+                b"text:p" | b"text:h" => {
+                    paragraph_depth = paragraph_depth.saturating_sub(1);
+                    if paragraph_depth > 0 {
+                        continue;
+                    }
+                    let text = std::mem::take(&mut paragraph);
+                    if in_cell {
+                        if !cell_text.is_empty() {
+                            cell_text.push(' ');
+                        }
+                        cell_text.push_str(&text);
+                    } else if let Some(n) = slide {
+                        if !text.trim().is_empty() {
+                            blocks.push(TextBlock { locator: format!("幻灯片 {}", n), text, page: None });
+                        }
+                    } else {
+                        paragraph_count += 1;
+                        if !text.trim().is_empty() {
+                            blocks.push(TextBlock { locator: format!("段落 {}", paragraph_count), text, page: None });
+                        }
+                    }
+                }
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    in_cell = false;
+                    if !cell_text.is_empty() {
+                        row_cells.push(std::mem::take(&mut cell_text));
+                    }
+                }
+                b"table:table-row" => {
+                    row_number += 1;
+                    if !row_cells.is_empty() {
+                        let name = table_name.clone().unwrap_or_default();
+                        blocks.push(TextBlock {
+                            locator: format!("工作表 {} 第 {} 行", name, row_number),
+                            text: row_cells.join("\t"),
+                            page: None,
+                        });
+                    }
+                    // 重复行（通常是空行）只占行号
+                    row_number += row_repeat - 1;
+                }
+                b"table:table" => table_name = None,
+                b"draw:page" => slide = None,
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    blocks
+}
+
+/// PDF 解析线程名额池
+struct PdfPool {
+    threads: Mutex<PdfThreads>,
+    freed: Condvar,
+}
+
+struct PdfThreads {
+    /// 正在运行的线程数（含已超时、仍在后台运行的线程）
+    running: usize,
+    /// 其中已超时、调用方不再等待的线程数
+    stuck: usize,
+}
+
+static PDF_POOL: PdfPool = PdfPool::new();
+
+// 名额状态，只在持有 `PdfPool::threads` 锁时读写
+const SLOT_RUNNING: u8 = 0;
+const SLOT_STUCK: u8 = 1;
+const SLOT_DONE: u8 = 2;
+
+/// 解析线程名额，线程结束时释放
+struct PdfSlot {
+    pool: &'static PdfPool,
+    state: Arc<AtomicU8>,
+}
+
+impl PdfPool {
+    const fn new() -> Self {
+        Self { threads: Mutex::new(PdfThreads { running: 0, stuck: 0 }), freed: Condvar::new() }
+    }
+
+    /// 在截止时间前等到空闲名额；超时的线程没法强行结束，靠名额上限防止越积越多。
+    /// 名额全部被卡住的线程占着时立即放弃，不必白等到截止时间
+    fn acquire(&'static self, deadline: Instant) -> Option<PdfSlot> {
+        let mut threads = self.threads.lock().unwrap();
+        while threads.running >= MAX_PDF_EXTRACT_THREADS {
+            if threads.stuck >= MAX_PDF_EXTRACT_THREADS {
+                return None;
+            }
+            let wait = deadline.checked_duration_since(Instant::now())?;
+            threads = self.freed.wait_timeout(threads, wait).unwrap().0;
+        }
+        threads.running += 1;
+        Some(PdfSlot { pool: self, state: Arc::new(AtomicU8::new(SLOT_RUNNING)) })
+    }
+
+    /// 调用方放弃等待：线程仍在运行时计为卡住，并唤醒等待者重新判断
+    fn mark_stuck(&self, state: &AtomicU8) {
+        let mut threads = self.threads.lock().unwrap();
+        if state.load(Ordering::Relaxed) == SLOT_RUNNING {
+            state.store(SLOT_STUCK, Ordering::Relaxed);
+            threads.stuck += 1;
+            self.freed.notify_all();
+        }
+    }
+}
+
+impl Drop for PdfSlot {
+    fn drop(&mut self) {
+        let mut threads = self.pool.threads.lock().unwrap();
+        if self.state.swap(SLOT_DONE, Ordering::Relaxed) == SLOT_STUCK {
+            threads.stuck -= 1;
+        }
+        threads.running -= 1;
+        self.pool.freed.notify_one();
+    }
+}
+
+/// PDF 在独立线程中解析，超出时间预算即放弃等待；该线程在下一页开始前发现超时后自行结束。
+/// pdf-extract 遇到畸形文件可能 panic，用 `catch_unwind` 转成错误（release 配置为 unwind 才有效）
+fn extract_pdf(path: &Path) -> Result<Vec<TextBlock>> {
+    let budget = Duration::from_millis(PDF_EXTRACT_BUDGET_MS);
+    let deadline = Instant::now() + budget;
+    let slot = PDF_POOL.acquire(deadline).ok_or(SkipReason::Timeout)?;
+    let state = slot.state.clone();
+    let (tx, rx) = mpsc::channel();
+    let owned = path.to_path_buf();
+    std::thread::spawn(move || {
+        let _slot = slot;
+        let result = std::panic::catch_unwind(|| extract_pdf_pages(&owned, deadline))
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("PDF 解析时崩溃")));
+        let _ = tx.send(result);
+    });
+
+    match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            PDF_POOL.mark_stuck(&state);
+            Err(SkipReason::Timeout.into())
+        }
+        Err(RecvTimeoutError::Disconnected) => anyhow::bail!("PDF 解析线程异常退出"),
+    }
+}
+
+/// 每页一个文本块
+fn extract_pdf_pages(path: &Path, deadline: Instant) -> Result<Vec<TextBlock>> {
+    let mut doc = pdf_extract::Document::load(path)?;
+    if doc.is_encrypted() {
+        // 只限制打印/编辑的 PDF 使用空用户密码，可以直接解密；真正需要密码的跳过
+        if doc.decrypt("").is_err() {
+            return Err(SkipReason::Encrypted.into());
+        }
+        doc.trailer.remove(b"Encrypt");
+    }
+
+    let mut blocks = Vec::new();
+    for page in doc.get_pages().into_keys() {
+        if Instant::now() >= deadline {
+            return Err(SkipReason::Timeout.into());
+        }
+        let mut text = String::new();
+        if let Err(e) = pdf_extract::output_doc_page(&doc, &mut pdf_extract::PlainTextOutput::new(&mut text), page) {
+            tracing::debug!("PDF {:?} 第 {} 页提取失败: {}", path, page, e);
+            continue;
+        }
+        if !text.trim().is_empty() {
+            blocks.push(TextBlock { locator: format!("第 {} 页", page), text, page: Some(page) });
+        }
+    }
+
+    // 全部页面都没有文字：扫描件或纯图片
+    if blocks.is_empty() {
+        return Err(SkipReason::NoTextLayer.into());
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_pdf_is_an_error() {
+        let path = std::env::temp_dir().join(format!("starsearch-broken-{}.pdf", std::process::id()));
+        std::fs::write(&path, b"%PDF-1.4\n1 0 obj << /Type /Pages /Kids [2 0 R] >> endobj\ntrailer << /Root 9 0 R >>\n%%EOF").unwrap();
+        assert!(extract_text(&path).is_err());
+        // 名额在解析线程结束后归还
+        assert!(PDF_POOL.acquire(Instant::now() + Duration::from_secs(1)).is_some());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn wedged_pool_skips_immediately() {
+        static POOL: PdfPool = PdfPool::new();
+        let far = Instant::now() + Duration::from_secs(60);
+        let slots: Vec<PdfSlot> = (0..MAX_PDF_EXTRACT_THREADS).map(|_| POOL.acquire(far).unwrap()).collect();
+        // 名额用完但线程未卡住：等到截止时间才放弃
+        let started = Instant::now();
+        assert!(POOL.acquire(Instant::now() + Duration::from_millis(50)).is_none());
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        for slot in &slots {
+            POOL.mark_stuck(&slot.state);
+        }
+        let started = Instant::now();
+        assert!(POOL.acquire(far).is_none());
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        // 卡住的线程结束后名额归还
+        drop(slots);
+        let threads = POOL.threads.lock().unwrap();
+        assert_eq!((threads.running, threads.stuck), (0, 0));
+        drop(threads);
+        assert!(POOL.acquire(far).is_some());
+    }
+
+    #[test]
+    fn finished_slot_is_not_marked_stuck() {
+        static POOL: PdfPool = PdfPool::new();
+        let slot = POOL.acquire(Instant::now() + Duration::from_secs(1)).unwrap();
+        let state = slot.state.clone();
+        drop(slot);
+        POOL.mark_stuck(&state);
+        assert_eq!(POOL.threads.lock().unwrap().stuck, 0);
+    }
+
+    fn zip_with(parts: &[(&str, &str)]) -> zip::ZipArchive<std::io::Cursor<Vec<u8>>> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in parts {
+            writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            std::io::Write::write_all(&mut writer, content.as_bytes()).unwrap();
+        }
+        zip::ZipArchive::new(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn oversized_parts_are_rejected() {
+        let big = "x".repeat(4096);
+        let mut zip = zip_with(&[("word/document.xml", &big)]);
+        assert_eq!(read_entry_limited(&mut zip, "word/document.xml", 4096).unwrap().len(), 4096);
+        assert!(read_entry_limited(&mut zip, "word/document.xml", 4095).is_err());
+        assert!(read_entry_limited(&mut zip, "content.xml", 4096).is_err());
+    }
+
+    #[test]
+    fn docx_paragraphs_keep_tabs_and_numbering() {
+        let xml = r#"<w:document><w:body>
+            <w:p><w:r><w:t>Hello</w:t></w:r><w:r><w:tab/><w:t xml:space="preserve">world &amp; co</w:t></w:r></w:p>
+            <w:p/>
+            <w:p><w:r><w:t>第二</w:t><w:br/><w:t>段</w:t></w:r></w:p>
+        </w:body></w:document>"#;
+        assert_eq!(collect_paragraphs(xml), ["Hello\tworld & co", "", "第二 段"]);
+        let blocks = extract_docx(xml);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!((blocks[1].locator.as_str(), blocks[1].text.as_str()), ("段落 3", "第二 段"));
+    }
+
+    fn blocks(blocks: &[TextBlock]) -> Vec<(&str, &str)> {
+        blocks.iter().map(|b| (b.locator.as_str(), b.text.as_str())).collect()
+    }
+
+    #[test]
+    fn pptx_slides_in_numeric_order() {
+        let slide = |text: &str| format!(r#"<p:sld><p:cSld><p:spTree><p:sp><p:txBody><a:p><a:r><a:t>{}</a:t></a:r></a:p><a:p/></p:txBody></p:sp></p:spTree></p:cSld></p:sld>"#, text);
+        let (s1, s2, s10) = (slide("标题页"), slide("第二页 &amp; 附注"), slide("结尾"));
+        let mut zip = zip_with(&[
+            ("[Content_Types].xml", "<Types/>"),
+            ("ppt/slides/slide10.xml", &s10),
+            ("ppt/slides/slide2.xml", &s2),
+            ("ppt/slides/slide1.xml", &s1),
+            ("ppt/slides/_rels/slide1.xml.rels", "<Relationships/>"),
+            ("ppt/slideLayouts/slideLayout1.xml", &slide("版式")),
+        ]);
+        let result = extract_pptx(&mut zip).unwrap();
+        assert_eq!(blocks(&result), [("幻灯片 1", "标题页"), ("幻灯片 2", "第二页 & 附注"), ("幻灯片 10", "结尾")]);
+        assert!(result.iter().all(|b| b.page.is_none()));
+    }
+
+    #[test]
+    fn odt_paragraphs_and_headings() {
+        let content = r#"<office:document-content><office:body><office:text>
+            <text:h text:outline-level="1">标题</text:h>
+            <text:p>第一<text:s/>段<text:tab/>制表<text:span>嵌套</text:span></text:p>
+            <text:p/>
+            <text:p>含<text:line-break/>换行 &lt;tag&gt;</text:p>
+        </office:text></office:body></office:document-content>"#;
+        let mut zip = zip_with(&[("mimetype", "application/vnd.oasis.opendocument.text"), ("content.xml", content)]);
+        let result = extract_odf(&read_entry(&mut zip, "content.xml").unwrap());
+        assert_eq!(blocks(&result), [("段落 1", "标题"), ("段落 2", "第一 段\t制表嵌套"), ("段落 4", "含 换行 <tag>")]);
+    }
+
+    #[test]
+    fn ods_rows_and_odp_slides() {
+        let ods = r#"<office:document-content><office:body><office:spreadsheet>
+            <table:table table:name="预算">
+                <table:table-row><table:table-cell><text:p>项目</text:p></table:table-cell><table:table-cell><text:p>金额</text:p></table:table-cell></table:table-row>
+                <table:table-row table:number-rows-repeated="3"><table:table-cell/></table:table-row>
+                <table:table-row><table:table-cell><text:p>房租</text:p></table:table-cell><table:covered-table-cell/><table:table-cell><text:p>3000</text:p></table:table-cell></table:table-row>
+            </table:table>
+        </office:spreadsheet></office:body></office:document-content>"#;
+        let mut zip = zip_with(&[("content.xml", ods)]);
+        let result = extract_odf(&read_entry(&mut zip, "content.xml").unwrap());
+        assert_eq!(blocks(&result), [("工作表 预算 第 1 行", "项目\t金额"), ("工作表 预算 第 5 行", "房租\t3000")]);
+
+        let odp = r#"<office:document-content><office:body><office:presentation>
+            <draw:page draw:name="p1"><draw:frame><draw:text-box><text:p>开场</text:p><text:p>要点</text:p></draw:text-box></draw:frame></draw:page>
+            <draw:page draw:name="p2"><draw:frame><draw:text-box><text:p/></draw:text-box></draw:frame></draw:page>
+            <draw:page draw:name="p3"><draw:frame><draw:text-box><text:p>结束</text:p></draw:text-box></draw:frame></draw:page>
+        </office:presentation></office:body></office:document-content>"#;
+        let mut zip = zip_with(&[("content.xml", odp)]);
+        let result = extract_odf(&read_entry(&mut zip, "content.xml").unwrap());
+        assert_eq!(blocks(&result), [("幻灯片 1", "开场"), ("幻灯片 1", "要点"), ("幻灯片 3", "结束")]);
+    }
+
+    #[test]
+    fn sheet_rows_resolve_shared_strings() {
+        let shared = parse_shared_strings(
+            r#"<sst><si><t>名称</t></si><si><r><t>富</t></r><r><t>文本</t></r></si></sst>"#,
+        );
+        assert_eq!(shared, ["名称", "富文本"]);
+        let sheet = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1"><v>42</v></c></row>
+            <row r="3"><c t="s"><v>1</v></c><c t="inlineStr"><is><t>内联</t></is></c><c t="s"><v>9</v></c></row>
+            <row r="4"><c r="A4"/></row>
+        </sheetData></worksheet>"#;
+        assert_eq!(parse_sheet_rows(sheet, &shared), [(1, "名称\t42".to_string()), (3, "富文本\t内联".to_string())]);
+    }
+}
@@ -0,0 +1,577 @@
+use anyhow::Result;
+use rayon::prelude::*;
+use redb::{Database, MultimapTableDefinition, ReadableTable, ReadableTableMetadata, TableDefinition, WriteTransaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::time::SystemTime;
+use tracing::{debug, info};
+
+use crate::config::{fulltext_db_path, BINARY_EXTENSIONS, MAX_GREP_FILE_SIZE};
+use crate::encoding;
+use crate::extract;
+
+// 路径 -> 文件戳；文档 ID -> 路径；文档 ID -> 词表（删除时用）；词 -> 文档 ID 集合；建立过索引的目录 -> 更新时间
+const FT_FILES: TableDefinition<&str, &[u8]> = TableDefinition::new("ft_files");
+const FT_PATHS: TableDefinition<u64, &str> = TableDefinition::new("ft_paths");
+const FT_DOC_TERMS: TableDefinition<u64, &[u8]> = TableDefinition::new("ft_doc_terms");
+const FT_POSTINGS: MultimapTableDefinition<&str, u64> = MultimapTableDefinition::new("ft_postings");
+const FT_META: TableDefinition<&str, u64> = TableDefinition::new("ft_meta");
+const FT_ROOTS: TableDefinition<&str, u64> = TableDefinition::new("ft_roots");
+
+/// 索引格式版本，分词规则变化时递增；版本不符的索引视为不存在，下次更新时整体重建
+const INDEX_VERSION: u64 = 2;
+
+/// 每个写事务处理的文件数，避免单个事务占用过多内存
+const COMMIT_BATCH: usize = 500;
+
+/// 索引词的最大长度；更长的词（多是哈希或编码数据）只索引前这么多个字符，查询词同样截断后按前缀查
+const MAX_TERM_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct FileStamp {
+    id: u64,
+    modified: u64,
+    size: u64,
+}
+
+/// 一次增量更新的统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexStats {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+/// 文件内容倒排索引：英文按单词，中日韩文字按单字和相邻双字。
+///
+/// 索引只反映上次 `update` 时的内容：之后新增或改动的文件在下次更新
+/// （`--fulltext-update` 或 `index build --fulltext`）前查不到
+pub struct FullTextIndex {
+    db: Database,
+}
+
+impl FullTextIndex {
+    /// 打开数据目录下的索引（不存在则创建）
+    pub fn open() -> Result<Self> {
+        Self::open_at(&fulltext_db_path())
+    }
+
+    pub fn open_at(path: &Path) -> Result<Self> {
+        let db = Database::create(path)?;
+        let tx = db.begin_write()?;
+        {
+            tx.open_table(FT_FILES)?;
+            tx.open_table(FT_PATHS)?;
+            tx.open_table(FT_DOC_TERMS)?;
+            tx.open_multimap_table(FT_POSTINGS)?;
+            tx.open_table(FT_META)?;
+            tx.open_table(FT_ROOTS)?;
+        }
+        tx.commit()?;
+        Ok(Self { db })
+    }
+
+    /// 打开已建立的索引；索引不存在、为空或格式过旧时返回 `None`，调用方应退回全量搜索
+    pub fn open_existing() -> Result<Option<Self>> {
+        if !fulltext_db_path().exists() {
+            return Ok(None);
+        }
+        let index = Self::open()?;
+        Ok(if index.is_current()? && index.file_count()? > 0 { Some(index) } else { None })
+    }
+
+    fn is_current(&self) -> Result<bool> {
+        let tx = self.db.begin_read()?;
+        Ok(tx.open_table(FT_META)?.get("version")?.map(|v| v.value()) == Some(INDEX_VERSION))
+    }
+
+    /// 打开覆盖全部 `roots` 的已建立索引，否则返回 `None`（此时应全量搜索，不然范围外的文件会被漏掉）
+    pub fn open_covering(roots: &[String]) -> Result<Option<Self>> {
+        let Some(index) = Self::open_existing()? else { return Ok(None) };
+        for root in roots {
+            if !index.covers(root)? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(index))
+    }
+
+    /// `root` 是否位于某个建立过索引的目录之内。只有部分子目录建过索引时不算覆盖，
+    /// 否则未索引子目录里的文件会被漏掉
+    pub fn covers(&self, root: &str) -> Result<bool> {
+        let tx = self.db.begin_read()?;
+        let roots = tx.open_table(FT_ROOTS)?;
+        for item in roots.iter()? {
+            let (indexed, _) = item?;
+            if Path::new(root).starts_with(indexed.value()) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// 已索引的文件数
+    pub fn file_count(&self) -> Result<u64> {
+        let tx = self.db.begin_read()?;
+        Ok(tx.open_table(FT_FILES)?.len()?)
+    }
+
+    /// 按修改时间和大小增量更新：只重建变化的文件，删除已不存在的文件
+    pub fn update(&self, roots: &[String]) -> Result<IndexStats> {
+        let mut stats = IndexStats::default();
+        if !self.is_current()? {
+            self.reset()?;
+        }
+
+        // 1. 遍历目录，收集可索引文件的 (路径, 修改时间, 大小)
+        let current: Vec<(String, u64, u64)> = roots
+            .par_iter()
+            .flat_map_iter(|root| {
+                ignore::WalkBuilder::new(root)
+                    .git_ignore(true)
+                    .hidden(false)
+                    .follow_links(false)
+                    .build()
+                    .flatten()
+                    .filter(|e| e.file_type().is_some_and(|ft| ft.is_file()))
+                    .filter_map(|e| {
+                        let meta = e.metadata().ok()?;
+                        if !is_indexable(e.path(), meta.len()) {
+                            return None;
+                        }
+                        let modified = meta
+                            .modified()
+                            .ok()
+                            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                            .map(|d| d.as_secs())
+                            .unwrap_or(0);
+                        Some((e.path().to_string_lossy().to_string(), modified, meta.len()))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        // 2. 与已有文件戳比对
+        let mut existing: HashMap<String, FileStamp> = HashMap::new();
+        {
+            let tx = self.db.begin_read()?;
+            let files = tx.open_table(FT_FILES)?;
+            for (k, v) in files.iter()?.flatten() {
+                let path = k.value();
+                if roots.iter().any(|r| Path::new(path).starts_with(r)) {
+                    if let Ok(stamp) = serde_json::from_slice::<FileStamp>(v.value()) {
+                        existing.insert(path.to_string(), stamp);
+                    }
+                }
+            }
+        }
+
+        let mut changed = Vec::new();
+        let mut seen = HashSet::with_capacity(current.len());
+        for (path, modified, size) in current {
+            seen.insert(path.clone());
+            match existing.get(&path) {
+                Some(s) if s.modified == modified && s.size == size => stats.unchanged += 1,
+                Some(_) => {
+                    stats.updated += 1;
+                    changed.push((path, modified, size));
+                }
+                None => {
+                    stats.added += 1;
+                    changed.push((path, modified, size));
+                }
+            }
+        }
+
+        // 3. 删除已不存在的文件
+        let gone: Vec<(&String, &FileStamp)> = existing.iter().filter(|(p, _)| !seen.contains(*p)).collect();
+        stats.removed = gone.len();
+        if !gone.is_empty() {
+            let tx = self.db.begin_write()?;
+            for (path, stamp) in gone {
+                remove_doc(&tx, path, stamp.id)?;
+            }
+            tx.commit()?;
+        }
+
+        // 4. 分批并行提取分词，串行写入
+        for chunk in changed.chunks(COMMIT_BATCH) {
+            let docs: Vec<(&String, u64, u64, BTreeSet<String>)> = chunk
+                .par_iter()
+                .map(|(path, modified, size)| {
+                    // 读取失败也记录文件戳，避免每次更新都重试
+                    let terms = read_text(Path::new(path)).map(|t| tokenize(&t)).unwrap_or_default();
+                    (path, *modified, *size, terms)
+                })
+                .collect();
+
+            let tx = self.db.begin_write()?;
+            {
+                let mut meta = tx.open_table(FT_META)?;
+                let mut next_id = meta.get("next_id")?.map(|v| v.value()).unwrap_or(1);
+
+                for (path, modified, size, terms) in docs {
+                    if let Some(old) = existing.get(path) {
+                        remove_doc(&tx, path, old.id)?;
+                    }
+                    let id = next_id;
+                    next_id += 1;
+
+                    let mut postings = tx.open_multimap_table(FT_POSTINGS)?;
+                    for term in &terms {
+                        postings.insert(term.as_str(), id)?;
+                    }
+                    let term_list: Vec<&String> = terms.iter().collect();
+                    tx.open_table(FT_DOC_TERMS)?.insert(id, serde_json::to_vec(&term_list)?.as_slice())?;
+                    tx.open_table(FT_PATHS)?.insert(id, path.as_str())?;
+                    let stamp = FileStamp { id, modified, size };
+                    tx.open_table(FT_FILES)?.insert(path.as_str(), serde_json::to_vec(&stamp)?.as_slice())?;
+                }
+                meta.insert("next_id", next_id)?;
+            }
+            tx.commit()?;
+            debug!("全文索引已写入 {} 个文件", chunk.len());
+        }
+
+        // 全部写入后才记录目录，中途失败的目录不会被当作已覆盖
+        let tx = self.db.begin_write()?;
+        {
+            let mut indexed = tx.open_table(FT_ROOTS)?;
+            let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            for root in roots {
+                indexed.insert(root.as_str(), now)?;
+            }
+        }
+        tx.commit()?;
+
+        info!(
+            "全文索引更新完成：新增 {}，更新 {}，删除 {}，未变 {}",
+            stats.added, stats.updated, stats.removed, stats.unchanged
+        );
+        Ok(stats)
+    }
+
+    /// 查询候选文件；查询中没有索引能回答的词（如纯标点、只有一个可能是词中片段的单词）
+    /// 时返回 `None`，由调用方退回全量 grep。`whole_word` 为整词匹配，此时查询两端的词也是完整的词
+    pub fn candidates(&self, query: &str, whole_word: bool) -> Result<Option<Vec<String>>> {
+        let terms = query_terms(query, whole_word);
+        if terms.is_empty() {
+            return Ok(None);
+        }
+
+        let tx = self.db.begin_read()?;
+        let postings = tx.open_multimap_table(FT_POSTINGS)?;
+        let mut ids: Option<HashSet<u64>> = None;
+
+        for (term, kind) in terms {
+            let mut matched = HashSet::new();
+            if kind == TermKind::Prefix {
+                // 英文词按前缀匹配，查询 "conf" 可以命中 "config"
+                for item in postings.range(term.as_str()..)? {
+                    let (key, values) = item?;
+                    if !key.value().starts_with(term.as_str()) {
+                        break;
+                    }
+                    for v in values {
+                        matched.insert(v?.value());
+                    }
+                }
+            } else {
+                for v in postings.get(term.as_str())? {
+                    matched.insert(v?.value());
+                }
+            }
+
+            let next: HashSet<u64> = match ids {
+                Some(prev) => prev.intersection(&matched).copied().collect(),
+                None => matched,
+            };
+            if next.is_empty() {
+                return Ok(Some(Vec::new()));
+            }
+            ids = Some(next);
+        }
+
+        let paths = tx.open_table(FT_PATHS)?;
+        let mut result = Vec::new();
+        for id in ids.unwrap_or_default() {
+            if let Some(p) = paths.get(id)? {
+                result.push(p.value().to_string());
+            }
+        }
+        Ok(Some(result))
+    }
+}
+
+impl FullTextIndex {
+    /// 清空全部表并写入当前格式版本
+    fn reset(&self) -> Result<()> {
+        let tx = self.db.begin_write()?;
+        tx.delete_table(FT_FILES)?;
+        tx.delete_table(FT_PATHS)?;
+        tx.delete_table(FT_DOC_TERMS)?;
+        tx.delete_multimap_table(FT_POSTINGS)?;
+        tx.delete_table(FT_META)?;
+        tx.delete_table(FT_ROOTS)?;
+        {
+            tx.open_table(FT_FILES)?;
+            tx.open_table(FT_PATHS)?;
+            tx.open_table(FT_DOC_TERMS)?;
+            tx.open_multimap_table(FT_POSTINGS)?;
+            tx.open_table(FT_ROOTS)?;
+            tx.open_table(FT_META)?.insert("version", INDEX_VERSION)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn remove_doc(tx: &WriteTransaction, path: &str, id: u64) -> Result<()> {
+    let mut doc_terms = tx.open_table(FT_DOC_TERMS)?;
+    if let Some(terms) = doc_terms.remove(id)? {
+        let terms: Vec<String> = serde_json::from_slice(terms.value()).unwrap_or_default();
+        let mut postings = tx.open_multimap_table(FT_POSTINGS)?;
+        for term in terms {
+            postings.remove(term.as_str(), id)?;
+        }
+    }
+    tx.open_table(FT_PATHS)?.remove(id)?;
+    tx.open_table(FT_FILES)?.remove(path)?;
+    Ok(())
+}
+
+/// 与内容搜索相同的过滤规则：文档总是索引，其余跳过二进制类型和超大文件
+fn is_indexable(path: &Path, size: u64) -> bool {
+    if extract::is_document(path) {
+        return true;
+    }
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    !BINARY_EXTENSIONS.contains(&ext.as_str()) && size <= MAX_GREP_FILE_SIZE
+}
+
+/// 读取可搜索的文本：文档走提取，文本文件按探测到的编码解码
+fn read_text(path: &Path) -> Option<String> {
+    if extract::is_document(path) {
+        let blocks = extract::extract_text(path).ok()?;
+        return Some(blocks.into_iter().map(|b| b.text).collect::<Vec<_>>().join("\n"));
+    }
+    let bytes = std::fs::read(path).ok()?;
+    let (file_encoding, binary) = encoding::sniff_file(path).ok()?;
+    if binary {
+        return None;
+    }
+    Some(file_encoding.decode(&bytes).0.into_owned())
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF     // 日文假名
+        | 0x3400..=0x4DBF   // 扩展 A
+        | 0x4E00..=0x9FFF   // 基本汉字
+        | 0xAC00..=0xD7AF   // 韩文音节
+        | 0xF900..=0xFAFF   // 兼容汉字
+        | 0x20000..=0x2A6DF // 扩展 B
+    )
+}
+
+/// 索引分词：字母数字按单词切分并转小写（下划线也作分隔），CJK 取单字和相邻双字
+pub fn tokenize(text: &str) -> BTreeSet<String> {
+    let mut terms = BTreeSet::new();
+    let mut word = String::new();
+    let mut prev_cjk: Option<char> = None;
+
+    let flush = |word: &mut String, terms: &mut BTreeSet<String>| {
+        if !word.is_empty() {
+            terms.insert(truncate_term(std::mem::take(word)));
+        }
+    };
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush(&mut word, &mut terms);
+            terms.insert(c.to_string());
+            if let Some(p) = prev_cjk {
+                terms.insert([p, c].iter().collect());
+            }
+            prev_cjk = Some(c);
+        } else if c.is_alphanumeric() {
+            prev_cjk = None;
+            word.extend(c.to_lowercase());
+        } else {
+            flush(&mut word, &mut terms);
+            prev_cjk = None;
+        }
+    }
+    flush(&mut word, &mut terms);
+    terms
+}
+
+/// 超过 `MAX_TERM_LEN` 个字符的词只保留开头部分
+fn truncate_term(mut term: String) -> String {
+    if let Some((cut, _)) = term.char_indices().nth(MAX_TERM_LEN) {
+        term.truncate(cut);
+    }
+    term
+}
+
+/// 查询词在索引中的查法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum TermKind {
+    /// 完整的词
+    Exact,
+    /// 查询以该词结尾，文中可能还有后续字母："conf" 可以命中 "config"
+    Prefix,
+}
+
+/// 查询分词。子串匹配时查询开头的词可能是文中某个词的后半截（"Config" 命中 "AppConfig"），
+/// 倒排索引答不了，直接丢弃；结尾的词按前缀查，两侧都有分隔的词按整词查。
+/// CJK 片段只用双字（单字片段用单字），任何位置都能查
+fn query_terms(query: &str, whole_word: bool) -> Vec<(String, TermKind)> {
+    let mut terms = Vec::new();
+    let mut word = String::new();
+    // 当前单词之前是否有分隔（标点、空白或 CJK 字符）
+    let mut bounded = whole_word;
+    let mut run: Vec<char> = Vec::new();
+
+    let flush_run = |run: &mut Vec<char>, terms: &mut Vec<(String, TermKind)>| {
+        if run.len() == 1 {
+            terms.push((run[0].to_string(), TermKind::Exact));
+        } else {
+            for pair in run.windows(2) {
+                terms.push((pair.iter().collect(), TermKind::Exact));
+            }
+        }
+        run.clear();
+    };
+
+    for c in query.chars() {
+        if is_cjk(c) || !c.is_alphanumeric() {
+            if !word.is_empty() && bounded {
+                terms.push((std::mem::take(&mut word), TermKind::Exact));
+            }
+            word.clear();
+            bounded = true;
+            if is_cjk(c) {
+                run.push(c);
+            } else {
+                flush_run(&mut run, &mut terms);
+            }
+        } else {
+            flush_run(&mut run, &mut terms);
+            word.extend(c.to_lowercase());
+        }
+    }
+    if !word.is_empty() && bounded {
+        terms.push((word, if whole_word { TermKind::Exact } else { TermKind::Prefix }));
+    }
+    flush_run(&mut run, &mut terms);
+    // 索引里长词只有前 MAX_TERM_LEN 个字符，查询词截断后按前缀查（截断后的整词可能对应文中更长的词）
+    let mut terms: Vec<(String, TermKind)> = terms
+        .into_iter()
+        .map(|(term, kind)| {
+            let truncated = truncate_term(term.clone());
+            if truncated.len() < term.len() { (truncated, TermKind::Prefix) } else { (term, kind) }
+        })
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+/// 查询是否为普通字面量（不含正则元字符），只有字面量查询能用索引筛选候选文件
+pub fn is_literal(query: &str) -> bool {
+    !query.chars().any(|c| "\\.+*?()|[]{}^$".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected(items: &[(&str, TermKind)]) -> Vec<(String, TermKind)> {
+        items.iter().map(|(t, k)| (t.to_string(), *k)).collect()
+    }
+
+    #[test]
+    fn leading_word_may_be_a_suffix() {
+        assert!(query_terms("Config", false).is_empty());
+        assert_eq!(query_terms("Config", true), expected(&[("config", TermKind::Exact)]));
+        assert_eq!(query_terms("foo bar baz", false), expected(&[("bar", TermKind::Exact), ("baz", TermKind::Prefix)]));
+        assert_eq!(query_terms("::new", false), expected(&[("new", TermKind::Prefix)]));
+    }
+
+    #[test]
+    fn cjk_uses_bigrams() {
+        assert_eq!(query_terms("搜索引擎", false), expected(&[("引擎", TermKind::Exact), ("搜索", TermKind::Exact), ("索引", TermKind::Exact)]));
+        assert_eq!(query_terms("配置Config", false), expected(&[("config", TermKind::Prefix), ("配置", TermKind::Exact)]));
+    }
+
+    #[test]
+    fn tokenize_splits_words_and_cjk() {
+        let t = tokenize("AppConfig::new() 中文");
+        assert!(t.contains("appconfig") && t.contains("new") && t.contains("中文") && t.contains("中"));
+    }
+
+    #[test]
+    fn candidates_never_miss_substring_hits() {
+        let dir = std::env::temp_dir().join(format!("starsearch-fulltext-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a.rs");
+        std::fs::write(&file, "let c = AppConfig::new();\n").unwrap();
+        std::fs::write(dir.join("b.rs"), "nothing here\n").unwrap();
+
+        let index = FullTextIndex::open_at(&dir.join("ft.redb")).unwrap();
+        let root = dir.to_string_lossy().to_string();
+        assert!(!index.covers(&root).unwrap());
+        index.update(std::slice::from_ref(&root)).unwrap();
+        assert!(index.covers(&root).unwrap());
+        assert!(!index.covers(&format!("{}-other", root)).unwrap());
+
+        let file = file.to_string_lossy().to_string();
+        // "Config" 是 "AppConfig" 的后半截，索引答不了
+        assert_eq!(index.candidates("Config", false).unwrap(), None);
+        assert_eq!(index.candidates("Config::ne", false).unwrap(), Some(vec![file.clone()]));
+        assert_eq!(index.candidates("appconfig", true).unwrap(), Some(vec![file]));
+        assert_eq!(index.candidates("config", true).unwrap(), Some(Vec::new()));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn parent_of_indexed_root_is_not_covered() {
+        let dir = std::env::temp_dir().join(format!("starsearch-fulltext-parent-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        std::fs::write(dir.join("a/x.txt"), "needle\n").unwrap();
+        std::fs::write(dir.join("b/y.txt"), "needle\n").unwrap();
+
+        let index = FullTextIndex::open_at(&dir.join("ft.redb")).unwrap();
+        let indexed = dir.join("a").to_string_lossy().to_string();
+        index.update(std::slice::from_ref(&indexed)).unwrap();
+        assert!(index.covers(&indexed).unwrap());
+        assert!(index.covers(&dir.join("a/sub").to_string_lossy()).unwrap());
+        // 父目录下还有未索引的 b/y.txt，不能用索引筛选
+        assert!(!index.covers(&dir.to_string_lossy()).unwrap());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn long_words_are_indexed_by_prefix() {
+        let dir = std::env::temp_dir().join(format!("starsearch-fulltext-long-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a.txt");
+        std::fs::write(&file, format!("token = {}\n", "k".repeat(70))).unwrap();
+
+        let index = FullTextIndex::open_at(&dir.join("ft.redb")).unwrap();
+        let root = dir.to_string_lossy().to_string();
+        index.update(std::slice::from_ref(&root)).unwrap();
+
+        let file = file.to_string_lossy().to_string();
+        assert_eq!(index.candidates("token = kkkk", false).unwrap(), Some(vec![file.clone()]));
+        assert_eq!(index.candidates(&"k".repeat(70), true).unwrap(), Some(vec![file]));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
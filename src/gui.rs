@@ -1,7 +1,10 @@
+use crate::preview::Preview;
 use crate::searcher::{SearchBackend, SearchEntry};
+use crate::thumbnail::{ThumbnailPool, PREVIEW_THUMBNAIL_SIZE, ROW_THUMBNAIL_SIZE};
 use chrono::Timelike;
 use eframe::egui;
 use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -97,6 +100,92 @@ pub struct StarSearchApp {
     // 主题图标
     day_icon: egui::TextureHandle,
     night_icon: egui::TextureHandle,
+
+    // 右侧预览栏（后台生成）
+    show_preview: bool,
+    preview: Option<PreviewCache>,
+    preview_worker: PreviewWorker,
+
+    // 图片缩略图（后台生成，按路径与边长缓存纹理）
+    thumbnail_pool: ThumbnailPool,
+    thumbnails: HashMap<(PathBuf, u32), ThumbnailState>,
+}
+
+enum ThumbnailState {
+    Ready {
+        texture: egui::TextureHandle,
+        source_width: u32,
+        source_height: u32,
+    },
+    Failed(String),
+}
+
+// 取缩略图纹理；尚未生成时提交给后台线程池（池内去重），本帧先返回 None
+fn lookup_thumbnail<'a>(
+    thumbnails: &'a HashMap<(PathBuf, u32), ThumbnailState>,
+    pool: &ThumbnailPool,
+    path: &std::path::Path,
+    size: u32,
+) -> Option<&'a ThumbnailState> {
+    let state = thumbnails.get(&(path.to_path_buf(), size));
+    if state.is_none() {
+        pool.request(path, size);
+    }
+    state
+}
+
+// 当前选中文件的预览，选中项或主题变化时重新生成；`result` 为 None 表示后台仍在生成
+struct PreviewCache {
+    path: PathBuf,
+    is_dark: bool,
+    result: Option<Result<Preview, String>>,
+}
+
+// 预览请求：（路径，是否目录，深色主题）
+type PreviewRequest = (PathBuf, bool, bool);
+// 预览结果：（路径，深色主题，预览或错误信息）
+type PreviewResult = (PathBuf, bool, Result<Preview, String>);
+
+// 后台预览线程：高亮、读目录、查索引、列压缩包成员都可能很慢，不能放在界面线程。
+// 只处理最新的请求，连续切换选中项时跳过中间的文件
+struct PreviewWorker {
+    sender: Sender<PreviewRequest>,
+    receiver: Receiver<PreviewResult>,
+}
+
+impl PreviewWorker {
+    fn new(backend: Arc<SearchBackend>) -> Self {
+        let (sender, requests) = channel::<PreviewRequest>();
+        let (results, receiver) = channel();
+        std::thread::Builder::new()
+            .name("preview".to_string())
+            .spawn(move || {
+                while let Ok(mut request) = requests.recv() {
+                    while let Ok(newer) = requests.try_recv() {
+                        request = newer;
+                    }
+                    let (path, is_dir, is_dark) = request;
+                    let result = if is_dir {
+                        crate::preview::preview_folder(&path, backend.list_children(&path)).map(Preview::Folder)
+                    } else {
+                        crate::preview::preview_file(&path, is_dark)
+                    };
+                    if results.send((path, is_dark, result.map_err(|e| e.to_string()))).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("无法创建预览线程");
+        Self { sender, receiver }
+    }
+
+    fn request(&self, path: PathBuf, is_dir: bool, is_dark: bool) {
+        let _ = self.sender.send((path, is_dir, is_dark));
+    }
+
+    fn poll(&self) -> Vec<PreviewResult> {
+        self.receiver.try_iter().collect()
+    }
 }
 
 impl StarSearchApp {
@@ -203,11 +292,13 @@ impl StarSearchApp {
         // 如果用户觉得界面太大或太小，可以通过系统缩放调整
         let _ppp = _cc.egui_ctx.pixels_per_point();
 
+        let backend = Arc::new(SearchBackend::new(app_dir.clone()));
         Self {
             query: String::new(),
             results: Vec::new(),
             category: SearchCategory::All,
-            backend: Arc::new(SearchBackend::new(app_dir.clone())),
+            preview_worker: PreviewWorker::new(backend.clone()),
+            backend,
             selected_index: 0,
             visible: true,
             app_dir,
@@ -219,8 +310,247 @@ impl StarSearchApp {
             is_dark,
             day_icon,
             night_icon,
+            show_preview: true,
+            preview: None,
+            thumbnail_pool: ThumbnailPool::new(),
+            thumbnails: HashMap::new(),
         }
     }
+
+    // 把后台生成好的缩略图上传为纹理
+    fn receive_thumbnails(&mut self, ctx: &egui::Context) {
+        for (path, size, result) in self.thumbnail_pool.poll() {
+            let state = match result {
+                Ok(thumb) => ThumbnailState::Ready {
+                    texture: ctx.load_texture(
+                        format!("thumb:{}:{}", size, path.display()),
+                        egui::ColorImage::from_rgba_unmultiplied([thumb.width as usize, thumb.height as usize], &thumb.rgba),
+                        egui::TextureOptions::LINEAR,
+                    ),
+                    source_width: thumb.source_width,
+                    source_height: thumb.source_height,
+                },
+                Err(msg) => ThumbnailState::Failed(msg),
+            };
+            self.thumbnails.insert((path, size), state);
+        }
+    }
+
+    // 右侧预览栏：语法高亮显示选中文件的开头部分，目录显示子项与汇总
+    fn show_preview_panel(&mut self, ctx: &egui::Context, theme: &MorandiTheme) {
+        let Some(entry) = self.results.get(self.selected_index) else { return };
+
+        if !entry.is_dir && crate::thumbnail::is_image(&entry.path) {
+            let state = lookup_thumbnail(&self.thumbnails, &self.thumbnail_pool, &entry.path, PREVIEW_THUMBNAIL_SIZE);
+            egui::SidePanel::right("preview_panel")
+                .resizable(true)
+                .default_width(420.0)
+                .frame(egui::Frame::none()
+                    .fill(theme.panel_bg)
+                    .rounding(egui::Rounding::same(12.0))
+                    .inner_margin(egui::Margin::same(12.0))
+                    .outer_margin(egui::Margin::same(1.0)))
+                .show(ctx, |ui| match state {
+                    Some(ThumbnailState::Ready { texture, source_width, source_height }) => {
+                        ui.label(egui::RichText::new(format!("{} × {} 像素", source_width, source_height))
+                            .size(12.0)
+                            .color(theme.text.linear_multiply(0.6)));
+                        ui.add_space(6.0);
+                        ui.add(egui::Image::new(texture).max_width(ui.available_width()).rounding(8.0));
+                    }
+                    Some(ThumbnailState::Failed(msg)) => {
+                        ui.label(egui::RichText::new(msg).size(14.0).color(theme.text.linear_multiply(0.6)));
+                    }
+                    None => {
+                        ui.spinner();
+                    }
+                });
+            return;
+        }
+
+        let stale = self.preview.as_ref().is_none_or(|p| p.path != entry.path || p.is_dark != self.is_dark);
+        if stale {
+            self.preview = Some(PreviewCache { path: entry.path.clone(), is_dark: self.is_dark, result: None });
+            self.preview_worker.request(entry.path.clone(), entry.is_dir, self.is_dark);
+        }
+        // 只接收与当前选中项一致的结果，过期的直接丢弃
+        for (path, is_dark, result) in self.preview_worker.poll() {
+            if let Some(cache) = self.preview.as_mut().filter(|c| c.path == path && c.is_dark == is_dark) {
+                cache.result = Some(result);
+            }
+        }
+        let Some(cache) = &self.preview else { return };
+
+        egui::SidePanel::right("preview_panel")
+            .resizable(true)
+            .default_width(420.0)
+            .frame(egui::Frame::none()
+                .fill(theme.panel_bg)
+                .rounding(egui::Rounding::same(12.0))
+                .inner_margin(egui::Margin::same(12.0))
+                .outer_margin(egui::Margin::same(1.0)))
+            .show(ctx, |ui| {
+                match &cache.result {
+                    Some(Ok(Preview::Text(preview))) => {
+                        ui.label(egui::RichText::new(format!("{} · {}", preview.syntax, preview.encoding))
+                            .size(12.0)
+                            .color(theme.text.linear_multiply(0.6)));
+                        ui.add_space(6.0);
+
+                        let [r, g, b] = preview.background;
+                        egui::Frame::none()
+                            .fill(egui::Color32::from_rgb(r, g, b))
+                            .rounding(8.0)
+                            .inner_margin(egui::Margin::same(8.0))
+                            .show(ui, |ui| {
+                                egui::ScrollArea::both().auto_shrink([false; 2]).show(ui, |ui| {
+                                    let font_id = egui::FontId::monospace(13.0);
+                                    for line in &preview.lines {
+                                        let mut job = egui::text::LayoutJob::default();
+                                        job.append(&format!("{:>4}  ", line.number), 0.0, egui::TextFormat {
+                                            font_id: font_id.clone(),
+                                            color: egui::Color32::GRAY,
+                                            ..Default::default()
+                                        });
+                                        for span in &line.spans {
+                                            let [r, g, b] = span.color;
+                                            job.append(&span.text, 0.0, egui::TextFormat {
+                                                font_id: font_id.clone(),
+                                                color: egui::Color32::from_rgb(r, g, b),
+                                                italics: span.italic,
+                                                underline: if span.underline {
+                                                    egui::Stroke::new(1.0, egui::Color32::from_rgb(r, g, b))
+                                                } else {
+                                                    egui::Stroke::NONE
+                                                },
+                                                ..Default::default()
+                                            });
+                                        }
+                                        ui.add(egui::Label::new(job).extend());
+                                    }
+                                    if preview.truncated {
+                                        ui.label(egui::RichText::new("…（仅预览开头部分）").size(12.0).color(egui::Color32::GRAY));
+                                    }
+                                });
+                            });
+                    }
+                    Some(Ok(Preview::Binary(preview))) => {
+                        egui::Grid::new("binary_summary").num_columns(2).spacing([12.0, 4.0]).show(ui, |ui| {
+                            for (label, value) in preview.summary() {
+                                ui.label(egui::RichText::new(label).size(12.0).color(theme.text.linear_multiply(0.6)));
+                                ui.label(egui::RichText::new(value).size(12.0).color(theme.text));
+                                ui.end_row();
+                            }
+                            // 版本资源字段（公司、描述、版权等）
+                            for (key, value) in preview.version_strings() {
+                                ui.label(egui::RichText::new(key).size(12.0).color(theme.text.linear_multiply(0.6)));
+                                ui.label(egui::RichText::new(value).size(12.0).color(theme.text));
+                                ui.end_row();
+                            }
+                        });
+                        ui.add_space(6.0);
+
+                        egui::ScrollArea::both().auto_shrink([false; 2]).show(ui, |ui| {
+                            for row in &preview.hex {
+                                ui.add(egui::Label::new(
+                                    egui::RichText::new(format!("{:08x}  {:<49}  {}", row.offset, row.hex, row.ascii))
+                                        .monospace()
+                                        .size(12.0)
+                                        .color(theme.text),
+                                ).extend());
+                            }
+                        });
+                    }
+                    Some(Ok(Preview::Folder(preview))) => {
+                        egui::Grid::new("folder_summary").num_columns(2).spacing([12.0, 4.0]).show(ui, |ui| {
+                            for (label, value) in preview.summary() {
+                                ui.label(egui::RichText::new(label).size(12.0).color(theme.text.linear_multiply(0.6)));
+                                ui.label(egui::RichText::new(value).size(12.0).color(theme.text));
+                                ui.end_row();
+                            }
+                        });
+
+                        if !preview.recent.is_empty() {
+                            ui.add_space(8.0);
+                            ui.label(egui::RichText::new("最近修改").size(12.0).strong().color(theme.text));
+                            for child in &preview.recent {
+                                let time = child
+                                    .modified
+                                    .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0))
+                                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                                    .unwrap_or_default();
+                                ui.label(egui::RichText::new(format!("{}  {}", time, child.name))
+                                    .size(12.0)
+                                    .color(theme.text.linear_multiply(0.8)));
+                            }
+                        }
+                        ui.add_space(8.0);
+
+                        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+                            for child in &preview.children {
+                                ui.horizontal(|ui| {
+                                    let icon = if child.is_dir { "📁" } else { "📄" };
+                                    ui.label(egui::RichText::new(format!("{} {}", icon, child.name)).size(13.0).color(theme.text));
+                                    if !child.is_dir {
+                                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                            ui.label(egui::RichText::new(format!("{} 字节", child.size))
+                                                .size(12.0)
+                                                .color(theme.text.linear_multiply(0.6)));
+                                        });
+                                    }
+                                });
+                            }
+                            if preview.truncated {
+                                ui.label(egui::RichText::new("…（仅显示部分子项）").size(12.0).color(egui::Color32::GRAY));
+                            }
+                        });
+                    }
+                    Some(Ok(Preview::Archive(preview))) => {
+                        egui::Grid::new("archive_summary").num_columns(2).spacing([12.0, 4.0]).show(ui, |ui| {
+                            for (label, value) in preview.summary() {
+                                ui.label(egui::RichText::new(label).size(12.0).color(theme.text.linear_multiply(0.6)));
+                                ui.label(egui::RichText::new(value).size(12.0).color(theme.text));
+                                ui.end_row();
+                            }
+                        });
+                        ui.add_space(8.0);
+
+                        egui::ScrollArea::both().auto_shrink([false; 2]).show(ui, |ui| {
+                            egui::Grid::new("archive_members").num_columns(4).spacing([12.0, 2.0]).striped(true).show(ui, |ui| {
+                                let muted = theme.text.linear_multiply(0.6);
+                                for member in &preview.members {
+                                    let icon = if member.is_dir { "📁" } else { "📄" };
+                                    ui.label(egui::RichText::new(format!("{} {}", icon, member.path)).size(12.0).color(theme.text));
+                                    if member.is_dir {
+                                        ui.label("");
+                                        ui.label("");
+                                    } else {
+                                        ui.label(egui::RichText::new(format!("{} 字节", member.size)).size(12.0).color(muted));
+                                        let ratio = crate::preview::compression_ratio(member.size, member.compressed_size);
+                                        ui.label(egui::RichText::new(ratio.unwrap_or_default()).size(12.0).color(muted));
+                                    }
+                                    let time = chrono::DateTime::from_timestamp(member.modified as i64, 0)
+                                        .filter(|_| member.modified > 0)
+                                        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                                        .unwrap_or_default();
+                                    ui.label(egui::RichText::new(time).size(12.0).color(muted));
+                                    ui.end_row();
+                                }
+                            });
+                            if preview.truncated {
+                                ui.label(egui::RichText::new("…（仅显示部分成员）").size(12.0).color(egui::Color32::GRAY));
+                            }
+                        });
+                    }
+                    Some(Err(msg)) => {
+                        ui.label(egui::RichText::new(msg).size(14.0).color(theme.text.linear_multiply(0.6)));
+                    }
+                    None => {
+                        ui.spinner();
+                    }
+                }
+            });
+    }
 }
 
 // 莫兰迪配色方案
@@ -298,6 +628,10 @@ impl eframe::App for StarSearchApp {
                 });
                 println!("[DEBUG] 排序完成");
 
+                // 只保留仍在结果中的缩略图纹理
+                let paths: std::collections::HashSet<&PathBuf> = res.iter().map(|r| &r.path).collect();
+                self.thumbnails.retain(|(path, _), _| paths.contains(path));
+
                 self.results = res;
                 self.selected_index = 0;
                 println!("[DEBUG] 状态更新完成");
@@ -332,7 +666,7 @@ impl eframe::App for StarSearchApp {
             // 异步启动文件打开
             let path_to_open = entry.path.clone();
             std::thread::spawn(move || {
-                let _ = open::that(&path_to_open);
+                let _ = crate::archive::open_path(&path_to_open);
             });
         }
 
@@ -357,6 +691,11 @@ impl eframe::App for StarSearchApp {
             MorandiTheme::light()
         };
 
+        self.receive_thumbnails(ctx);
+        if self.show_preview {
+            self.show_preview_panel(ctx, &theme);
+        }
+
         // 自定义主面板框架
         let panel_frame = egui::Frame::none()
             .fill(theme.panel_bg)
@@ -422,6 +761,15 @@ impl eframe::App for StarSearchApp {
                                 ui.painter().rect_filled(min_btn.rect, egui::Rounding::same(4.0), theme.accent.linear_multiply(0.2));
                             }
 
+                            // 预览栏开关
+                            let preview_btn = ui.add(egui::Button::new(egui::RichText::new("👁").size(14.0))
+                                .fill(if self.show_preview { theme.accent.linear_multiply(0.2) } else { egui::Color32::TRANSPARENT })
+                                .stroke(egui::Stroke::NONE))
+                                .on_hover_text("预览");
+                            if preview_btn.clicked() {
+                                self.show_preview = !self.show_preview;
+                            }
+
                             // 主题切换按钮 - 使用PNG图标
                             let icon_size = egui::vec2(24.0, 24.0);
                             let theme_resp = if self.is_dark {
@@ -677,7 +1025,18 @@ impl eframe::App for StarSearchApp {
                                                 
                                                 // 第一栏：图标 + 名称 (35%)
                                                 ui.allocate_ui_with_layout(egui::vec2(total_width * 0.35, 48.0), egui::Layout::left_to_right(egui::Align::Center), |ui: &mut egui::Ui| {
-                                                    ui.label(egui::RichText::new(res.icon()).size(28.0)); 
+                                                    let thumb = if crate::thumbnail::is_image(&res.path) {
+                                                        lookup_thumbnail(&self.thumbnails, &self.thumbnail_pool, &res.path, ROW_THUMBNAIL_SIZE)
+                                                    } else {
+                                                        None
+                                                    };
+                                                    if let Some(ThumbnailState::Ready { texture, .. }) = thumb {
+                                                        ui.add(egui::Image::new(texture)
+                                                            .max_size(egui::vec2(40.0, 40.0))
+                                                            .rounding(4.0));
+                                                    } else {
+                                                        ui.label(egui::RichText::new(res.icon()).size(28.0));
+                                                    }
                                                     ui.add_space(12.0);
                                                     
                                                     let name = &res.name;
@@ -749,7 +1108,7 @@ impl eframe::App for StarSearchApp {
                                     }
 
                                     if let Some(path) = action_open {
-                                        let _ = open::that(path);
+                                        let _ = crate::archive::open_path(&path);
                                     }
                                 });
                         });
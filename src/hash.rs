@@ -0,0 +1,60 @@
+/// 64 位 FNV-1a 哈希：结果只取决于输入字节，可用作磁盘缓存文件名与临时目录名。
+/// `DefaultHasher` 的算法不保证跨 Rust 版本稳定，换工具链后会让已有缓存全部失效
+#[derive(Debug, Clone, Copy)]
+pub struct StableHasher(u64);
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(FNV_OFFSET)
+    }
+}
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> &mut Self {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+        self
+    }
+
+    /// 变长字段先写长度，避免 ("ab", "c") 与 ("a", "bc") 相同
+    pub fn write_str(&mut self, s: &str) -> &mut Self {
+        self.write_u64(s.len() as u64).write(s.as_bytes())
+    }
+
+    pub fn write_u64(&mut self, value: u64) -> &mut Self {
+        self.write(&value.to_le_bytes())
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_reference_values() {
+        // FNV-1a 64 的公开测试向量
+        assert_eq!(StableHasher::new().finish(), 0xcbf29ce484222325);
+        assert_eq!(StableHasher::new().write(b"a").finish(), 0xaf63dc4c8601ec8c);
+        assert_eq!(StableHasher::new().write(b"foobar").finish(), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn fields_are_length_prefixed() {
+        let ab_c = StableHasher::new().write_str("ab").write_str("c").finish();
+        let a_bc = StableHasher::new().write_str("a").write_str("bc").finish();
+        assert_ne!(ab_c, a_bc);
+    }
+}
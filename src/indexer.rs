@@ -23,7 +23,8 @@ impl Indexer {
 
         tokio::task::spawn_blocking(move || {
             drives.par_iter().for_each(|drive| {
-                let drive_path = format!("{}:\\", drive);
+                // 非 Windows 平台没有盘符，`/` 即根目录
+                let drive_path = if *drive == '/' { "/".to_string() } else { format!("{}:\\", drive) };
                 let mut drive_files = Vec::new();
                 
                 for entry in WalkDir::new(&drive_path)
@@ -56,6 +57,7 @@ impl Indexer {
                         is_dir: entry.file_type().is_dir(),
                         drive: *drive,
                         score: 0.0,
+                        ..Default::default()
                     });
 
                     // 每 5000 个文件同步一次，防止占用过多临时内存
@@ -1,20 +1,41 @@
 use anyhow::Result;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::windows::named_pipe::ClientOptions;
 use crate::types::{SearchRequest, SearchResponse};
 
+#[cfg(windows)]
 pub const PIPE_NAME: &str = r"\\.\pipe\starsearch_pipe";
 
+/// 非 Windows 平台的服务端点（Unix 域套接字）
+#[cfg(not(windows))]
+pub fn socket_path() -> std::path::PathBuf {
+    crate::config::data_dir().join("starsearch.sock")
+}
+
+/// 服务端点的显示名称
+pub fn endpoint() -> String {
+    #[cfg(windows)]
+    {
+        PIPE_NAME.to_string()
+    }
+    #[cfg(not(windows))]
+    {
+        socket_path().to_string_lossy().to_string()
+    }
+}
+
 pub async fn client_request(request: &SearchRequest) -> Result<SearchResponse> {
-    let mut client = ClientOptions::new().open(PIPE_NAME)?;
-    
+    #[cfg(windows)]
+    let mut client = tokio::net::windows::named_pipe::ClientOptions::new().open(PIPE_NAME)?;
+    #[cfg(not(windows))]
+    let mut client = tokio::net::UnixStream::connect(socket_path()).await?;
+
     let request_data = serde_json::to_vec(request)?;
     client.write_all(&request_data).await?;
-    
+
     // 不要 shutdown，因为我们是请求-响应模式，直接读取
     let mut response_data = vec![0u8; 65536]; // 64KB 应该够了
     let n = client.read(&mut response_data).await?;
-    
+
     let response = serde_json::from_slice(&response_data[..n])?;
     Ok(response)
 }
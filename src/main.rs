@@ -1,11 +1,32 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+#[allow(dead_code)]
+mod archive;
 mod config;
 #[allow(dead_code)]
 mod content_search;
 mod dpi;
+#[allow(dead_code)]
+mod encoding;
+#[allow(dead_code)]
+mod extract;
+#[allow(dead_code)]
+mod fulltext;
 mod gui;
+#[allow(dead_code)]
+mod hash;
+#[allow(dead_code)]
+mod pe;
+#[allow(dead_code)]
+mod preview;
+#[allow(dead_code)]
+mod ranking;
+#[allow(dead_code)]
+mod replace;
 mod searcher;
+mod thumbnail;
+#[allow(dead_code)]
+mod types;
 
 use crate::gui::StarSearchApp;
 use eframe::egui;
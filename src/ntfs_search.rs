@@ -1,16 +1,20 @@
 use anyhow::{Context, Result};
 use ntfs::Ntfs;
-use redb::{Database, TableDefinition, ReadableTable};
-use std::fs::OpenOptions;
-use std::io::BufReader;
+use redb::{Database, ReadableTable, ReadableTableMetadata, TableDefinition};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use walkdir::WalkDir;
 use tracing::{info, warn, error};
+#[cfg(windows)]
 use std::os::windows::fs::OpenOptionsExt;
 
 use crate::config::GLOBAL_CONFIG;
-use crate::types::FileEntry;
+use crate::query::LocalQuery;
+use crate::sort::{self, entry_value};
+use crate::types::{EntryKind, FileEntry, SortKey};
 
 // 索引表定义
 const FILE_TABLE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("local_files");
@@ -20,11 +24,30 @@ pub struct LocalNtfsSearcher {
     memory_index: Arc<RwLock<Vec<FileEntry>>>,
     ready: Arc<RwLock<bool>>,
     db: Option<Arc<Database>>,
+    options: NtfsScanOptions,
+}
+
+/// MFT 扫描的可选项，均较慢，默认关闭
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NtfsScanOptions {
+    /// 额外遍历全部 MFT 记录以恢复已删除文件
+    pub include_deleted: bool,
+    /// 读取每个文件的记录以列出备用数据流
+    pub include_streams: bool,
+    /// 打开本机压缩包，把成员作为虚拟路径加入索引
+    pub include_archives: bool,
+}
+
+impl NtfsScanOptions {
+    /// 是否开启了任一可选项
+    pub fn any(&self) -> bool {
+        self.include_deleted || self.include_streams || self.include_archives
+    }
 }
 
 impl LocalNtfsSearcher {
     pub fn new() -> Self {
-        let db = match Database::create(dirs::cache_dir().unwrap_or_default().join("starsearch_local.redb")) {
+        let db = match Database::create(crate::config::local_index_db_path()) {
             Ok(d) => Some(Arc::new(d)),
             Err(_) => None,
         };
@@ -33,10 +56,28 @@ impl LocalNtfsSearcher {
             memory_index: Arc::new(RwLock::new(Vec::with_capacity(500_000))),
             ready: Arc::new(RwLock::new(false)),
             db,
+            options: NtfsScanOptions::default(),
         }
     }
 
-    #[allow(dead_code)]
+    /// 开启已删除文件扫描，结果可通过 `deleted:` 修饰符检索
+    pub fn with_deleted(mut self, enabled: bool) -> Self {
+        self.options.include_deleted = enabled;
+        self
+    }
+
+    /// 开启备用数据流扫描，结果可通过 `ads:` 修饰符检索
+    pub fn with_streams(mut self, enabled: bool) -> Self {
+        self.options.include_streams = enabled;
+        self
+    }
+
+    /// 开启压缩包成员索引（zip/tar/tar.gz/7z）
+    pub fn with_archives(mut self, enabled: bool) -> Self {
+        self.options.include_archives = enabled;
+        self
+    }
+
     pub async fn is_ready(&self) -> bool {
         *self.ready.read().await
     }
@@ -44,8 +85,10 @@ impl LocalNtfsSearcher {
     pub async fn load_all_drives(&self) -> Result<usize> {
         info!("开始加载驱动器索引...");
         
-        // 1. 尝试从缓存加载
-        if let Some(count) = self.load_from_cache().await {
+        // 1. 尝试从缓存加载：缓存只有默认扫描的结果，开启已删除文件、数据流或压缩包时总是重新扫描
+        if self.options.any() {
+            info!("已开启可选扫描项 {:?}，跳过缓存", self.options);
+        } else if let Some(count) = self.load_from_cache().await {
             if count > 0 {
                 info!("成功从缓存加载 {} 条记录", count);
                 *self.ready.write().await = true;
@@ -53,7 +96,11 @@ impl LocalNtfsSearcher {
             }
         }
 
-        // 2. 全盘扫描
+        self.rebuild().await
+    }
+
+    /// 忽略缓存重新扫描全部驱动器（非 Windows 平台扫描 `local_work_dirs`），完成后覆盖缓存
+    pub async fn rebuild(&self) -> Result<usize> {
         let mut all_entries = Vec::with_capacity(500_000);
         let drives = get_all_drives();
         info!("发现驱动器: {:?}", drives);
@@ -71,6 +118,18 @@ impl LocalNtfsSearcher {
             }
         }
 
+        #[cfg(not(windows))]
+        for root in &GLOBAL_CONFIG.local_work_dirs {
+            info!("正在扫描目录 {}", root);
+            all_entries.extend(self.scan_walkdir_root(root, '/').await?);
+        }
+
+        if self.options.include_archives {
+            let members = expand_archives(&all_entries);
+            info!("压缩包成员索引完成，获得 {} 个虚拟条目", members.len());
+            all_entries.extend(members);
+        }
+
         let count = all_entries.len();
         if count == 0 {
             warn!("警告：未发现任何文件。");
@@ -108,7 +167,7 @@ impl LocalNtfsSearcher {
 
         // 否则或 MFT 失败，使用 WalkDir
         info!("正在使用 WalkDir 扫描 {} 盘...", drive);
-        self.scan_walkdir(drive).await
+        self.scan_walkdir_root(&format!("{}:\\", drive), drive).await
     }
 
     /// ★ MFT 直读 (关键修复: 共享读模式) ★
@@ -116,96 +175,38 @@ impl LocalNtfsSearcher {
         let drive_path = format!(r"\\.\{}:", drive);
         
         // 使用 FILE_SHARE_READ (0x01) | FILE_SHARE_WRITE (0x02) 避免冲突
-        let file = OpenOptions::new()
-            .read(true)
-            .share_mode(0x01 | 0x02) 
+        let mut options = OpenOptions::new();
+        options.read(true);
+        #[cfg(windows)]
+        options.share_mode(0x01 | 0x02);
+        let file = options
             .open(&drive_path)
             .map_err(|e| anyhow::anyhow!("无法打开驱动器 {}: {}", drive_path, e))?;
 
         let mut reader = BufReader::with_capacity(1024 * 1024, file);
-        
-        let ntfs = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            Ntfs::new(&mut reader)
-        })).map_err(|_| anyhow::anyhow!("NTFS 解析发生 Panic ({})", drive))?
-           .map_err(|e| anyhow::anyhow!("NTFS 解析失败 ({}): {}", drive, e))?;
-
-        let root = ntfs.root_directory(&mut reader)?;
-        let mut entries = Vec::with_capacity(100_000);
-        
-        let mut stack = vec![(root, format!("{}:", drive))];
-
-        while let Some((dir, current_path)) = stack.pop() {
-            let index = match dir.directory_index(&mut reader) {
-                Ok(i) => i,
-                Err(_) => continue,
-            };
-            
-            let mut iter = index.entries();
-            while let Some(entry_result) = iter.next(&mut reader) {
-                let entry = match entry_result {
-                    Ok(e) => e,
-                    Err(_) => continue,
-                };
-
-                let file_name = match entry.key() {
-                    Some(Ok(fb)) => fb,
-                    _ => continue,
-                };
-
-                let name = match file_name.name().to_string() {
-                    Ok(n) => n,
-                    Err(_) => continue,
-                };
-                
-                if name == "." || name == ".." {
-                    continue;
-                }
-
-                let full_path = format!("{}\\{}", current_path, name);
-                
-                let path_upper = full_path.to_uppercase();
-                
-                // 排除一些极其庞大且无关紧要的目录以提速
-                if path_upper.contains(r"\$RECYCLE.BIN") ||
-                   path_upper.contains(r"\SYSTEM VOLUME INFORMATION") ||
-                   (path_upper.contains(r"C:\WINDOWS") && !path_upper.contains("EXPLORER.EXE")) {
-                    continue;
-                }
-
-                let is_dir = file_name.file_attributes().contains(ntfs::structured_values::NtfsFileAttributeFlags::IS_DIRECTORY);
-                
-                entries.push(FileEntry {
-                    name: name.clone(),
-                    path: full_path.clone(),
-                    extension: std::path::Path::new(&name).extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase(),
-                    size: 0, // MFT 直读暂不处理 size 以追求速度
-                    modified: 0,
-                    is_dir,
-                    drive,
-                    score: 0.0,
-                });
-
-                if is_dir {
-                    if let Ok(sub_file) = entry.to_file(&ntfs, &mut reader) {
-                        stack.push((sub_file, full_path));
-                    }
-                }
+        scan_ntfs_reader(&mut reader, &format!("{}:", drive), drive, GLOBAL_CONFIG.local_max_cache, self.options, false)
+    }
 
-                if entries.len() >= GLOBAL_CONFIG.local_max_cache {
-                    return Ok(entries);
-                }
-            }
-        }
+    /// 加载 NTFS 原始磁盘镜像（dd/.img），结果路径以镜像文件名为前缀
+    ///
+    /// `offset` 为分区在镜像内的字节偏移；为 `None` 时自动探测（分区镜像或 MBR/GPT 整盘镜像）。
+    pub async fn load_image(&self, image_path: &Path, offset: Option<u64>) -> Result<usize> {
+        let path = image_path.to_path_buf();
+        let options = self.options;
+        let entries = tokio::task::spawn_blocking(move || scan_ntfs_image(&path, offset, options)).await??;
+        let count = entries.len();
+        info!("镜像 {:?} 扫描完成，获得 {} 个文件", image_path, count);
 
-        Ok(entries)
+        self.memory_index.write().await.extend(entries);
+        *self.ready.write().await = true;
+        Ok(count)
     }
 
-    async fn scan_walkdir(&self, drive: char) -> Result<Vec<FileEntry>> {
-        let root = format!("{}:\\", drive);
+    async fn scan_walkdir_root(&self, root: &str, drive: char) -> Result<Vec<FileEntry>> {
         let mut entries = Vec::new();
 
         // 增加深度到 20，适应更深的目录结构
-        for entry in WalkDir::new(&root)
+        for entry in WalkDir::new(root)
             .max_depth(20)
             .follow_links(false)
             .into_iter()
@@ -239,6 +240,7 @@ impl LocalNtfsSearcher {
                 is_dir: metadata.is_dir(),
                 drive,
                 score: 0.0,
+                ..Default::default()
             });
 
             if entries.len() >= GLOBAL_CONFIG.local_max_cache {
@@ -276,12 +278,18 @@ impl LocalNtfsSearcher {
 
     pub async fn search(&self, query: &str, max_results: usize) -> Vec<FileEntry> {
         let index = self.memory_index.read().await;
-        if query.is_empty() {
-            return index.iter().take(max_results).cloned().collect();
+        let query = LocalQuery::parse(query);
+        if query.keyword.is_empty() {
+            return index.iter()
+                .filter(|e| query.accepts(e.kind) && query.matches_filters(e))
+                .take(max_results)
+                .cloned()
+                .collect();
         }
 
-        let query_upper = query.to_uppercase();
+        let query_upper = query.keyword.to_uppercase();
         let mut results: Vec<FileEntry> = index.iter()
+            .filter(|e| query.accepts(e.kind) && query.matches_filters(e))
             .filter(|e| e.name.to_uppercase().contains(&query_upper) || e.path.to_uppercase().contains(&query_upper))
             .take(max_results * 5)
             .cloned()
@@ -298,6 +306,34 @@ impl LocalNtfsSearcher {
         results.into_iter().take(max_results).collect()
     }
 
+    /// 按指定字段取前 `max_results` 条；`SortKey::Score` 沿用 `search` 的相关度顺序
+    pub async fn search_sorted(&self, query: &str, max_results: usize, key: SortKey, reverse: bool) -> Vec<FileEntry> {
+        if key == SortKey::Score {
+            if !reverse {
+                return self.search(query, max_results).await;
+            }
+            // 与其他字段一致：反向取匹配度最低的 N 条，而不是把前 N 条倒过来
+            let total = self.memory_index.read().await.len();
+            let mut results = self.search(query, total).await;
+            results.reverse();
+            results.truncate(max_results);
+            return results;
+        }
+
+        let index = self.memory_index.read().await;
+        let query = LocalQuery::parse(query);
+        let query_upper = query.keyword.to_uppercase();
+        let matched: Vec<&FileEntry> = index.iter()
+            .filter(|e| query.accepts(e.kind) && query.matches_filters(e))
+            .filter(|e| query_upper.is_empty() || e.name.to_uppercase().contains(&query_upper) || e.path.to_uppercase().contains(&query_upper))
+            .collect();
+        sort::top_k(matched, max_results, key, reverse, |e| entry_value(e, key))
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    #[cfg(windows)]
     pub fn is_admin() -> bool {
         use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
         use windows::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_QUERY, TOKEN_ELEVATION};
@@ -316,13 +352,35 @@ impl LocalNtfsSearcher {
         false
     }
 
+    #[cfg(not(windows))]
+    pub fn is_admin() -> bool {
+        false
+    }
+
+    /// 缓存中的条目数；缓存不存在或无法读取时为 None
+    pub fn cached_count(&self) -> Option<u64> {
+        let db = self.db.as_ref()?;
+        let tx = db.begin_read().ok()?;
+        let table = tx.open_table(FILE_TABLE).ok()?;
+        table.len().ok()
+    }
+
+    /// 当前内存索引的全部条目
+    pub async fn entries(&self) -> Vec<FileEntry> {
+        self.memory_index.read().await.clone()
+    }
+
     async fn save_to_cache(&self) -> Result<()> {
         let db = self.db.as_ref().context("数据库未初始化")?;
         let tx = db.begin_write()?;
+        // 整表重写：缓存按路径做键，逐条覆盖不会删掉已不存在的文件，也不会删掉以前写入的非普通条目
+        tx.delete_table(FILE_TABLE)?;
         {
             let mut table = tx.open_table(FILE_TABLE)?;
             let index = self.memory_index.read().await;
-            for entry in index.iter() {
+            // 只保存普通文件：已删除记录可能同路径重复且随时变化，数据流与压缩包成员只在显式开启时返回，
+            // 写进共用缓存会让之后的普通搜索也带出它们
+            for entry in index.iter().filter(|e| e.kind == EntryKind::Normal) {
                 let key = entry.path.as_bytes();
                 let val = serde_json::to_vec(entry)?;
                 table.insert(key, val.as_slice())?;
@@ -332,6 +390,7 @@ impl LocalNtfsSearcher {
         Ok(())
     }
 
+    #[cfg(windows)]
     pub fn get_all_drives() -> Vec<char> {
         use windows::Win32::Storage::FileSystem::GetLogicalDrives;
         let mut drives = Vec::new();
@@ -346,6 +405,11 @@ impl LocalNtfsSearcher {
         }
         drives
     }
+
+    #[cfg(not(windows))]
+    pub fn get_all_drives() -> Vec<char> {
+        Vec::new()
+    }
 }
 
 fn is_admin() -> bool {
@@ -355,3 +419,710 @@ fn is_admin() -> bool {
 fn get_all_drives() -> Vec<char> {
     LocalNtfsSearcher::get_all_drives()
 }
+
+/// 并行读取索引中的压缩包，生成成员虚拟条目
+fn expand_archives(entries: &[FileEntry]) -> Vec<FileEntry> {
+    use rayon::prelude::*;
+    entries
+        .par_iter()
+        .filter(|e| !e.is_dir && e.kind == EntryKind::Normal && crate::archive::is_archive(Path::new(&e.path)))
+        .flat_map_iter(|e| crate::archive::member_entries(&e.path, e.drive))
+        .collect()
+}
+
+/// 遍历 NTFS 目录树，`root_path` 作为所有结果路径的前缀（如 `C:` 或 `disk.img`）
+///
+/// 硬链接按 MFT 记录号去重，其余链接路径记入 `links`；8.3 短名索引项直接跳过。
+/// `read_metadata` 为 true 时读取每个文件的记录取大小和修改时间，否则两者为 0。
+fn scan_ntfs_reader<R: Read + Seek>(
+    reader: &mut R,
+    root_path: &str,
+    drive: char,
+    limit: usize,
+    options: NtfsScanOptions,
+    read_metadata: bool,
+) -> Result<Vec<FileEntry>> {
+    let ntfs = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        Ntfs::new(reader)
+    })).map_err(|_| anyhow::anyhow!("NTFS 解析发生 Panic ({})", root_path))?
+       .map_err(|e| anyhow::anyhow!("NTFS 解析失败 ({}): {}", root_path, e))?;
+
+    let root = ntfs.root_directory(reader)?;
+    let mut entries = LinkedEntries::default();
+    
+    let mut stack = vec![(root, root_path.to_string())];
+
+    'walk: while let Some((dir, current_path)) = stack.pop() {
+        let index = match dir.directory_index(reader) {
+            Ok(i) => i,
+            Err(_) => continue,
+        };
+        
+        let mut iter = index.entries();
+        while let Some(entry_result) = iter.next(reader) {
+            let entry = match entry_result {
+                Ok(e) => e,
+                Err(_) => continue,
+            };
+
+            let file_name = match entry.key() {
+                Some(Ok(fb)) => fb,
+                _ => continue,
+            };
+
+            // 同一文件的 8.3 短名另有独立索引项，跳过以免重复
+            if file_name.namespace() == ntfs::structured_values::NtfsFileNamespace::Dos {
+                continue;
+            }
+
+            let name = match file_name.name().to_string() {
+                Ok(n) => n,
+                Err(_) => continue,
+            };
+            
+            if name == "." || name == ".." {
+                continue;
+            }
+
+            let full_path = format!("{}\\{}", current_path, name);
+            
+            let path_upper = full_path.to_uppercase();
+            
+            // 排除一些极其庞大且无关紧要的目录以提速
+            if path_upper.contains(r"\$RECYCLE.BIN") ||
+               path_upper.contains(r"\SYSTEM VOLUME INFORMATION") ||
+               (path_upper.contains(r"C:\WINDOWS") && !path_upper.contains("EXPLORER.EXE")) {
+                continue;
+            }
+
+            let is_dir = file_name.file_attributes().contains(ntfs::structured_values::NtfsFileAttributeFlags::IS_DIRECTORY);
+
+            let record = entry.file_reference().file_record_number();
+            let added = entries.push(record, FileEntry {
+                name: name.clone(),
+                path: full_path.clone(),
+                extension: std::path::Path::new(&name).extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase(),
+                size: 0, // 本机盘 MFT 直读不读文件记录以追求速度，镜像在下面补上
+                modified: 0,
+                is_dir,
+                drive,
+                score: 0.0,
+                ..Default::default()
+            });
+            if !added {
+                continue;
+            }
+
+            if is_dir || options.include_streams || read_metadata {
+                if let Ok(file) = entry.to_file(&ntfs, reader) {
+                    if read_metadata {
+                        let (size, modified) = file_metadata(&file, reader);
+                        if let Some(last) = entries.entries.last_mut() {
+                            last.size = if is_dir { 0 } else { size };
+                            last.modified = modified;
+                        }
+                    }
+                    if options.include_streams {
+                        for (stream, size) in data_streams(&file, reader) {
+                            entries.entries.push(FileEntry {
+                                name: format!("{}:{}", name, stream),
+                                path: format!("{}:{}", full_path, stream),
+                                size,
+                                drive,
+                                kind: EntryKind::Stream,
+                                ..Default::default()
+                            });
+                        }
+                    }
+                    if is_dir {
+                        stack.push((file, full_path));
+                    }
+                }
+            }
+
+            if entries.entries.len() >= limit {
+                break 'walk;
+            }
+        }
+    }
+
+    let mut entries = entries.entries;
+    if options.include_deleted {
+        match scan_deleted_records(&ntfs, reader, root_path, drive) {
+            Ok(deleted) => {
+                info!("{}: 从 MFT 恢复 {} 个已删除条目", root_path, deleted.len());
+                entries.extend(deleted);
+            }
+            Err(e) => warn!("{}: 已删除记录扫描失败: {}", root_path, e),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// 遍历结果，按 MFT 记录号合并硬链接
+#[derive(Default)]
+struct LinkedEntries {
+    entries: Vec<FileEntry>,
+    /// 记录号 -> 在 `entries` 中的位置
+    seen_records: std::collections::HashMap<u64, usize>,
+}
+
+impl LinkedEntries {
+    /// 新记录加入结果并返回 true；已见过的记录只把路径记入其 `links`，返回 false
+    fn push(&mut self, record: u64, entry: FileEntry) -> bool {
+        if let Some(&existing) = self.seen_records.get(&record) {
+            self.entries[existing].links.push(entry.path);
+            return false;
+        }
+        self.seen_records.insert(record, self.entries.len());
+        self.entries.push(entry);
+        true
+    }
+}
+
+/// 根目录的 MFT 记录号
+const ROOT_RECORD_NUMBER: u64 = 5;
+/// 前 16 条为 $MFT、$Bitmap 等系统元文件
+const FIRST_USER_RECORD_NUMBER: u64 = 16;
+
+/// 目录及已删除文件的 MFT 记录摘要，用于重建路径
+struct MftRecord {
+    name: String,
+    parent: u64,
+    parent_sequence: u16,
+    sequence: u16,
+    in_use: bool,
+    is_dir: bool,
+}
+
+/// 遍历全部 MFT 记录，找出未使用（已删除）的记录并尽量重建完整路径
+///
+/// 父目录已被复用或链条断裂时，条目挂到 `<root>\$OrphanFiles` 下。
+fn scan_deleted_records<R: Read + Seek>(ntfs: &Ntfs, reader: &mut R, root_path: &str, drive: char) -> Result<Vec<FileEntry>> {
+    let mft = ntfs.file(reader, 0)?;
+    let mft_size = match mft.data(reader, "") {
+        Some(item) => item?.to_attribute()?.value_length(),
+        None => anyhow::bail!("$MFT 缺少 $DATA 属性"),
+    };
+    let record_count = mft_size / ntfs.file_record_size() as u64;
+    let bitmap = read_cluster_bitmap(ntfs, reader).ok();
+
+    let mut records = std::collections::HashMap::new();
+    let mut deleted = Vec::new();
+
+    for number in FIRST_USER_RECORD_NUMBER..record_count {
+        let file = match ntfs.file(reader, number) {
+            Ok(f) => f,
+            Err(_) => continue,
+        };
+        let in_use = file.flags().contains(ntfs::NtfsFileFlags::IN_USE);
+        let is_dir = file.is_directory();
+        if in_use && !is_dir {
+            continue;
+        }
+
+        let file_name = match best_file_name(&file, reader) {
+            Some(n) => n,
+            None => continue,
+        };
+        let name = match file_name.name().to_string() {
+            Ok(n) => n,
+            Err(_) => continue,
+        };
+        let parent_ref = file_name.parent_directory_reference();
+
+        if !in_use {
+            let (size, recoverable) = inspect_data_runs(&file, reader, ntfs, bitmap.as_deref());
+            let modified = file
+                .info()
+                .map(|info| nt_time_to_unix(info.modification_time().nt_timestamp()))
+                .unwrap_or(0);
+            deleted.push((number, FileEntry {
+                name: name.clone(),
+                path: String::new(),
+                extension: if is_dir { String::new() } else { Path::new(&name).extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase() },
+                size,
+                modified,
+                is_dir,
+                drive,
+                score: 0.0,
+                kind: EntryKind::Deleted,
+                recoverable,
+                ..Default::default()
+            }));
+        }
+
+        records.insert(number, MftRecord {
+            name,
+            parent: parent_ref.file_record_number(),
+            parent_sequence: parent_ref.sequence_number(),
+            sequence: file.sequence_number(),
+            in_use,
+            is_dir,
+        });
+    }
+
+    for (number, entry) in deleted.iter_mut() {
+        entry.path = resolve_record_path(&records, *number, root_path);
+    }
+    Ok(deleted.into_iter().map(|(_, e)| e).collect())
+}
+
+/// 沿父目录引用向上拼接路径；删除时序列号会加一，因此已删除父目录允许相差 1
+fn resolve_record_path(records: &std::collections::HashMap<u64, MftRecord>, number: u64, root_path: &str) -> String {
+    let mut components = Vec::new();
+    let mut current = match records.get(&number) {
+        Some(r) => r,
+        None => return format!(r"{}\$OrphanFiles", root_path),
+    };
+    components.push(current.name.as_str());
+
+    loop {
+        if current.parent == ROOT_RECORD_NUMBER {
+            break;
+        }
+        let parent = records.get(&current.parent).filter(|p| {
+            p.is_dir
+                && (p.sequence == current.parent_sequence
+                    || (!p.in_use && p.sequence == current.parent_sequence.wrapping_add(1)))
+        });
+        match parent {
+            Some(p) if components.len() < 256 => {
+                components.push(p.name.as_str());
+                current = p;
+            }
+            _ => {
+                components.push("$OrphanFiles");
+                break;
+            }
+        }
+    }
+
+    components.reverse();
+    format!(r"{}\{}", root_path, components.join(r"\"))
+}
+
+/// 从文件记录读取 (大小, 修改时间)：大小取无名 $DATA，时间取 $STANDARD_INFORMATION
+/// （索引项里 $FILE_NAME 的副本只在改名时更新，不可靠）
+fn file_metadata<R: Read + Seek>(file: &ntfs::NtfsFile, reader: &mut R) -> (u64, u64) {
+    let size = match file.data(reader, "") {
+        Some(Ok(item)) => item.to_attribute().map(|a| a.value_length()).unwrap_or(0),
+        _ => 0,
+    };
+    let modified = file
+        .info()
+        .map(|info| nt_time_to_unix(info.modification_time().nt_timestamp()))
+        .unwrap_or(0);
+    (size, modified)
+}
+
+/// 列出具名 $DATA 属性（备用数据流），返回 (流名, 大小)
+fn data_streams<R: Read + Seek>(file: &ntfs::NtfsFile, reader: &mut R) -> Vec<(String, u64)> {
+    let mut attributes = Vec::new();
+    let mut iter = file.attributes();
+    while let Some(item) = iter.next(reader) {
+        let attribute = match item.as_ref().map(|i| i.to_attribute()) {
+            Ok(Ok(a)) => a,
+            _ => continue,
+        };
+        let name = attribute.name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        attributes.push((attribute.ty().ok(), name, attribute.value_length()));
+    }
+    named_data_streams(attributes)
+}
+
+/// 从 (属性类型, 属性名, 大小) 中挑出具名 $DATA；无名 $DATA 是文件本身的内容
+fn named_data_streams(attributes: Vec<(Option<ntfs::NtfsAttributeType>, String, u64)>) -> Vec<(String, u64)> {
+    attributes
+        .into_iter()
+        .filter(|(ty, name, _)| *ty == Some(ntfs::NtfsAttributeType::Data) && !name.is_empty())
+        .map(|(_, name, size)| (name, size))
+        .collect()
+}
+
+/// 优先取长文件名，避免得到 8.3 短名
+fn best_file_name<R: Read + Seek>(file: &ntfs::NtfsFile, reader: &mut R) -> Option<ntfs::structured_values::NtfsFileName> {
+    use ntfs::structured_values::NtfsFileNamespace;
+    [NtfsFileNamespace::Win32AndDos, NtfsFileNamespace::Win32, NtfsFileNamespace::Posix]
+        .into_iter()
+        .find_map(|ns| file.name(reader, Some(ns), None).and_then(|r| r.ok()))
+        .or_else(|| file.name(reader, None, None).and_then(|r| r.ok()))
+}
+
+/// 返回 (数据大小, 数据是否完整)：常驻数据总是完整；非常驻数据要求所有簇在 $Bitmap 中仍为空闲
+fn inspect_data_runs<R: Read + Seek>(file: &ntfs::NtfsFile, reader: &mut R, ntfs: &Ntfs, bitmap: Option<&[u8]>) -> (u64, Option<bool>) {
+    let item = match file.data(reader, "") {
+        Some(Ok(item)) => item,
+        _ => return (0, None),
+    };
+    let attribute = match item.to_attribute() {
+        Ok(a) => a,
+        Err(_) => return (0, None),
+    };
+    let size = attribute.value_length();
+
+    let value = match attribute.value(reader) {
+        Ok(v) => v,
+        Err(_) => return (size, Some(false)),
+    };
+    let runs = match value {
+        ntfs::attribute_value::NtfsAttributeValue::Resident(_) => return (size, Some(true)),
+        ntfs::attribute_value::NtfsAttributeValue::NonResident(v) => v.data_runs(),
+        ntfs::attribute_value::NtfsAttributeValue::AttributeListNonResident(_) => return (size, None),
+    };
+    let bitmap = match bitmap {
+        Some(b) => b,
+        None => return (size, None),
+    };
+
+    let cluster_size = ntfs.cluster_size() as u64;
+    for run in runs {
+        let run = match run {
+            Ok(r) => r,
+            Err(_) => return (size, Some(false)),
+        };
+        // 稀疏数据段没有实际簇
+        let start = match run.data_position().value() {
+            Some(p) => p.get() / cluster_size,
+            None => continue,
+        };
+        let clusters = run.allocated_size().div_ceil(cluster_size);
+        for lcn in start..start + clusters {
+            let allocated = bitmap
+                .get((lcn / 8) as usize)
+                .map(|byte| byte & (1 << (lcn % 8)) != 0)
+                .unwrap_or(true);
+            if allocated {
+                return (size, Some(false));
+            }
+        }
+    }
+    (size, Some(true))
+}
+
+/// 读取 $Bitmap（记录 6），每一位代表一个簇是否已分配
+fn read_cluster_bitmap<R: Read + Seek>(ntfs: &Ntfs, reader: &mut R) -> Result<Vec<u8>> {
+    let file = ntfs.file(reader, 6)?;
+    let item = file.data(reader, "").context("$Bitmap 缺少 $DATA 属性")??;
+    let attribute = item.to_attribute()?;
+    let mut value = attribute.value(reader)?.attach(reader);
+    let mut bitmap = Vec::new();
+    value.read_to_end(&mut bitmap)?;
+    Ok(bitmap)
+}
+
+/// NTFS 时间（1601 年起的 100ns 计数）转 Unix 秒
+fn nt_time_to_unix(nt: u64) -> u64 {
+    (nt / 10_000_000).saturating_sub(11_644_473_600)
+}
+
+/// 扫描 NTFS 原始镜像文件，可在 Linux 上离线检索取证镜像
+pub fn scan_ntfs_image(image_path: &Path, offset: Option<u64>, options: NtfsScanOptions) -> Result<Vec<FileEntry>> {
+    let mut file = File::open(image_path)
+        .with_context(|| format!("无法打开镜像 {:?}", image_path))?;
+
+    let offset = match offset {
+        Some(o) => o,
+        None => probe_ntfs_offset(&mut file)?
+            .with_context(|| format!("未在镜像 {:?} 中找到 NTFS 分区，请手动指定偏移", image_path))?,
+    };
+    info!("镜像 {:?} 使用分区偏移 {} 字节", image_path, offset);
+
+    let image_name = image_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| image_path.to_string_lossy().to_string());
+
+    let mut reader = BufReader::with_capacity(1024 * 1024, OffsetReader::new(file, offset)?);
+    // 镜像是离线分析，值得多读一次文件记录换取大小和修改时间（按大小、时间排序要用）
+    scan_ntfs_reader(&mut reader, &image_name, ' ', usize::MAX, options, true)
+}
+
+const SECTOR_SIZE: u64 = 512;
+
+/// 探测镜像内 NTFS 分区的起始偏移：先看分区镜像本身，再查 MBR / GPT 分区表
+fn probe_ntfs_offset<R: Read + Seek>(reader: &mut R) -> Result<Option<u64>> {
+    if is_ntfs_boot_sector(reader, 0)? {
+        return Ok(Some(0));
+    }
+
+    let mut mbr = [0u8; 512];
+    reader.seek(SeekFrom::Start(0))?;
+    if reader.read_exact(&mut mbr).is_err() || mbr[510..512] != [0x55, 0xAA] {
+        return Ok(None);
+    }
+
+    let mut candidates = Vec::new();
+    let mut is_gpt = false;
+    for i in 0..4 {
+        let part = &mbr[0x1BE + i * 16..0x1BE + (i + 1) * 16];
+        let part_type = part[4];
+        let start_lba = u32::from_le_bytes([part[8], part[9], part[10], part[11]]) as u64;
+        if part_type == 0xEE {
+            is_gpt = true;
+        } else if part_type != 0 && start_lba != 0 {
+            candidates.push(start_lba);
+        }
+    }
+
+    if is_gpt {
+        candidates.extend(gpt_partition_starts(reader)?);
+    }
+
+    for lba in candidates {
+        // 损坏的分区表可能给出超大的 LBA
+        let Some(offset) = lba.checked_mul(SECTOR_SIZE) else { continue };
+        if is_ntfs_boot_sector(reader, offset)? {
+            return Ok(Some(offset));
+        }
+    }
+    Ok(None)
+}
+
+/// GPT 分区表项的大小：规范要求 128 × 2^n，这里只接受 128..=4096 且为 8 的倍数
+const GPT_ENTRY_SIZES: std::ops::RangeInclusive<u64> = 128..=4096;
+
+/// 读取 GPT 分区表（最多 128 项）中各分区的起始 LBA；头部不合法或镜像截断时返回已读到的部分
+fn gpt_partition_starts<R: Read + Seek>(reader: &mut R) -> Result<Vec<u64>> {
+    let mut starts = Vec::new();
+    let mut header = [0u8; 92];
+    reader.seek(SeekFrom::Start(SECTOR_SIZE))?;
+    if reader.read_exact(&mut header).is_err() || &header[0..8] != b"EFI PART" {
+        return Ok(starts);
+    }
+
+    let entries_lba = u64::from_le_bytes(header[72..80].try_into()?);
+    let entry_count = u32::from_le_bytes(header[80..84].try_into()?) as u64;
+    let entry_size = u32::from_le_bytes(header[84..88].try_into()?) as u64;
+    if !GPT_ENTRY_SIZES.contains(&entry_size) || !entry_size.is_multiple_of(8) {
+        return Ok(starts);
+    }
+    let Some(table_offset) = entries_lba.checked_mul(SECTOR_SIZE) else { return Ok(starts) };
+
+    let mut entry = vec![0u8; entry_size as usize];
+    for i in 0..entry_count.min(128) {
+        let Some(offset) = table_offset.checked_add(i * entry_size) else { break };
+        reader.seek(SeekFrom::Start(offset))?;
+        if reader.read_exact(&mut entry).is_err() {
+            break;
+        }
+        let first_lba = u64::from_le_bytes(entry[32..40].try_into()?);
+        if first_lba != 0 {
+            starts.push(first_lba);
+        }
+    }
+    Ok(starts)
+}
+
+fn is_ntfs_boot_sector<R: Read + Seek>(reader: &mut R, offset: u64) -> Result<bool> {
+    let mut oem = [0u8; 11];
+    reader.seek(SeekFrom::Start(offset))?;
+    if reader.read_exact(&mut oem).is_err() {
+        return Ok(false);
+    }
+    Ok(&oem[3..11] == b"NTFS    ")
+}
+
+/// 将读取位置整体平移到分区起点，使整盘镜像中的分区对 `ntfs` 看起来像独立卷
+struct OffsetReader<R> {
+    inner: R,
+    offset: u64,
+}
+
+impl<R: Seek> OffsetReader<R> {
+    fn new(mut inner: R, offset: u64) -> std::io::Result<Self> {
+        inner.seek(SeekFrom::Start(offset))?;
+        Ok(Self { inner, offset })
+    }
+}
+
+impl<R: Read> Read for OffsetReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for OffsetReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(p) => SeekFrom::Start(p + self.offset),
+            other => other,
+        };
+        let absolute = self.inner.seek(pos)?;
+        absolute.checked_sub(self.offset).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek 越过分区起点")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, size: u64) -> FileEntry {
+        FileEntry { name: name.to_string(), path: format!("/data/{}", name), size, ..Default::default() }
+    }
+
+    async fn searcher(names: &[(&str, u64)]) -> LocalNtfsSearcher {
+        let searcher = LocalNtfsSearcher::new();
+        *searcher.memory_index.write().await = names.iter().map(|(n, s)| entry(n, *s)).collect();
+        searcher
+    }
+
+    fn names(entries: Vec<FileEntry>) -> Vec<String> {
+        entries.into_iter().map(|e| e.name).collect()
+    }
+
+    fn mft_record(name: &str, parent: u64, parent_sequence: u16, sequence: u16, in_use: bool) -> MftRecord {
+        MftRecord { name: name.to_string(), parent, parent_sequence, sequence, in_use, is_dir: true }
+    }
+
+    #[test]
+    fn record_paths_follow_parent_references() {
+        let records: std::collections::HashMap<u64, MftRecord> = [
+            (30, mft_record("docs", ROOT_RECORD_NUMBER, 5, 1, true)),
+            (31, mft_record("2024", 30, 1, 3, true)),
+            (40, MftRecord { is_dir: false, ..mft_record("a.txt", 31, 3, 2, false) }),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(resolve_record_path(&records, 40, "C:"), r"C:\docs\2024\a.txt");
+        // 根目录下的文件
+        assert_eq!(resolve_record_path(&records, 30, "C:"), r"C:\docs");
+        // 不在表中的记录
+        assert_eq!(resolve_record_path(&records, 99, "C:"), r"C:\$OrphanFiles");
+    }
+
+    #[test]
+    fn record_paths_detect_reused_or_missing_parents() {
+        let records: std::collections::HashMap<u64, MftRecord> = [
+            // 父目录记录已被复用：序列号对不上
+            (30, mft_record("reused", ROOT_RECORD_NUMBER, 5, 7, true)),
+            (40, mft_record("a.txt", 30, 4, 2, false)),
+            // 父目录同样已删除，序列号比引用大 1
+            (31, mft_record("gone", ROOT_RECORD_NUMBER, 5, 5, false)),
+            (41, mft_record("b.txt", 31, 4, 2, false)),
+            // 仍在使用的父目录不允许相差 1
+            (32, mft_record("live", ROOT_RECORD_NUMBER, 5, 5, true)),
+            (42, mft_record("c.txt", 32, 4, 2, false)),
+            // 父记录不存在
+            (43, mft_record("d.txt", 77, 1, 2, false)),
+            // 父记录是文件而不是目录
+            (33, MftRecord { is_dir: false, ..mft_record("file", ROOT_RECORD_NUMBER, 5, 1, true) }),
+            (44, mft_record("e.txt", 33, 1, 2, false)),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(resolve_record_path(&records, 40, "img"), r"img\$OrphanFiles\a.txt");
+        assert_eq!(resolve_record_path(&records, 41, "img"), r"img\gone\b.txt");
+        assert_eq!(resolve_record_path(&records, 42, "img"), r"img\$OrphanFiles\c.txt");
+        assert_eq!(resolve_record_path(&records, 43, "img"), r"img\$OrphanFiles\d.txt");
+        assert_eq!(resolve_record_path(&records, 44, "img"), r"img\$OrphanFiles\e.txt");
+    }
+
+    #[test]
+    fn record_path_cycles_are_cut() {
+        // 损坏的 MFT 中父引用成环
+        let records: std::collections::HashMap<u64, MftRecord> =
+            [(30, mft_record("x", 31, 1, 1, true)), (31, mft_record("y", 30, 1, 1, true))].into_iter().collect();
+        let path = resolve_record_path(&records, 30, "C:");
+        assert!(path.starts_with(r"C:\$OrphanFiles\"), "{}", path);
+    }
+
+    #[test]
+    fn hard_links_are_merged_by_record() {
+        let mut entries = LinkedEntries::default();
+        assert!(entries.push(40, FileEntry { path: r"C:\a\report.txt".to_string(), ..entry("report.txt", 5) }));
+        assert!(entries.push(41, entry("other.txt", 1)));
+        assert!(!entries.push(40, FileEntry { path: r"C:\b\report-link.txt".to_string(), ..entry("report-link.txt", 5) }));
+        assert!(!entries.push(40, FileEntry { path: r"C:\c\copy.txt".to_string(), ..entry("copy.txt", 5) }));
+
+        assert_eq!(names(entries.entries.clone()), ["report.txt", "other.txt"]);
+        assert_eq!(entries.entries[0].links, [r"C:\b\report-link.txt", r"C:\c\copy.txt"]);
+        assert!(entries.entries[1].links.is_empty());
+    }
+
+    #[test]
+    fn only_named_data_attributes_are_streams() {
+        use ntfs::NtfsAttributeType;
+        let streams = named_data_streams(vec![
+            (Some(NtfsAttributeType::StandardInformation), String::new(), 72),
+            (Some(NtfsAttributeType::FileName), String::new(), 90),
+            (Some(NtfsAttributeType::Data), String::new(), 1000),
+            (Some(NtfsAttributeType::Data), "Zone.Identifier".to_string(), 26),
+            (Some(NtfsAttributeType::IndexRoot), "$I30".to_string(), 48),
+            (None, "broken".to_string(), 1),
+            (Some(NtfsAttributeType::Data), "thumb".to_string(), 0),
+        ]);
+        assert_eq!(streams, [("Zone.Identifier".to_string(), 26), ("thumb".to_string(), 0)]);
+    }
+
+    #[tokio::test]
+    async fn cache_keeps_only_normal_entries() {
+        let path = std::env::temp_dir().join(format!("starsearch-cache-{}.redb", std::process::id()));
+        let db = Arc::new(Database::create(&path).unwrap());
+        let with_db = |searcher: LocalNtfsSearcher| LocalNtfsSearcher { db: Some(db.clone()), ..searcher };
+
+        let first = with_db(searcher(&[("a.txt", 1), ("b.txt", 2)]).await);
+        {
+            let mut index = first.memory_index.write().await;
+            index[1].kind = EntryKind::Deleted;
+            index.push(FileEntry { kind: EntryKind::Stream, ..entry("a.txt:zone", 0) });
+        }
+        first.save_to_cache().await.unwrap();
+
+        let second = with_db(LocalNtfsSearcher::new());
+        assert_eq!(second.load_from_cache().await, Some(1));
+        assert_eq!(names(second.entries().await), ["a.txt"]);
+        drop((first, second, db));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn reversed_score_returns_the_lowest_ranked() {
+        let s = searcher(&[("report.txt", 1), ("report-final-v2.txt", 2), ("report-v1.txt", 3), ("r.txt", 4)]).await;
+        assert_eq!(names(s.search_sorted("report", 2, SortKey::Score, false).await), ["report.txt", "report-v1.txt"]);
+        assert_eq!(names(s.search_sorted("report", 2, SortKey::Score, true).await), ["report-final-v2.txt", "report-v1.txt"]);
+    }
+
+    #[tokio::test]
+    async fn sorted_by_size_scans_every_match() {
+        let mut items: Vec<(String, u64)> = (0..1000).map(|i| (format!("log{}.txt", i), 7)).collect();
+        items.push(("log-big.txt".to_string(), 100_000));
+        let refs: Vec<(&str, u64)> = items.iter().map(|(n, s)| (n.as_str(), *s)).collect();
+        let s = searcher(&refs).await;
+        assert_eq!(names(s.search_sorted("log", 1, SortKey::Size, false).await), ["log-big.txt"]);
+    }
+
+    /// 保护性 MBR + GPT 头，分区表位于 LBA 2
+    fn gpt_image(entry_count: u32, entry_size: u32) -> Vec<u8> {
+        let mut image = vec![0u8; 4096];
+        image[0x1BE + 4] = 0xEE;
+        image[510..512].copy_from_slice(&[0x55, 0xAA]);
+        image[512..520].copy_from_slice(b"EFI PART");
+        image[512 + 72..512 + 80].copy_from_slice(&2u64.to_le_bytes());
+        image[512 + 80..512 + 84].copy_from_slice(&entry_count.to_le_bytes());
+        image[512 + 84..512 + 88].copy_from_slice(&entry_size.to_le_bytes());
+        image
+    }
+
+    #[test]
+    fn probe_rejects_bad_gpt_and_short_images() {
+        let probe = |image: Vec<u8>| probe_ntfs_offset(&mut std::io::Cursor::new(image)).unwrap();
+        assert_eq!(probe(Vec::new()), None);
+        assert_eq!(probe(vec![0u8; 100]), None);
+        for size in [0, 16, 130, 8192, u32::MAX] {
+            assert_eq!(probe(gpt_image(4, size)), None);
+        }
+        // 头部截断、分区表超出镜像末尾
+        assert_eq!(probe(gpt_image(4, 128)[..520].to_vec()), None);
+        assert_eq!(probe(gpt_image(128, 128)), None);
+
+        // 第一项指向 LBA 6 的 NTFS 引导扇区
+        let mut image = gpt_image(1, 128);
+        image[1024 + 32..1024 + 40].copy_from_slice(&6u64.to_le_bytes());
+        image[6 * 512 + 3..6 * 512 + 11].copy_from_slice(b"NTFS    ");
+        assert_eq!(probe(image), Some(6 * 512));
+    }
+}
@@ -0,0 +1,405 @@
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// 资源类型 RT_VERSION
+const RT_VERSION: u32 = 16;
+/// 版本资源最大读取长度
+const MAX_VERSION_RESOURCE: u32 = 64 * 1024;
+/// VS_FIXEDFILEINFO 签名
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xFEEF_04BD;
+
+/// PE（exe/dll/sys）头部信息
+#[derive(Debug, Clone, Serialize)]
+pub struct PeInfo {
+    /// 目标架构（x86/x64/ARM64 等）
+    pub machine: String,
+    /// 子系统（Windows GUI/控制台/驱动等）
+    pub subsystem: String,
+    /// PE32+（64 位）
+    pub is_64bit: bool,
+    pub is_dll: bool,
+    /// 链接时间戳（Unix 秒，可能被可复现构建置为固定值）
+    pub timestamp: u32,
+    pub sections: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<VersionInfo>,
+}
+
+/// 版本资源（VS_VERSIONINFO）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VersionInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_version: Option<String>,
+    /// StringFileInfo 中的字段（CompanyName、FileDescription 等）
+    pub strings: BTreeMap<String, String>,
+}
+
+struct Section {
+    name: String,
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+    raw_size: u32,
+}
+
+/// 解析 PE 头；不是 PE 文件时返回错误
+pub fn parse(path: &Path) -> Result<PeInfo> {
+    let mut file = File::open(path)?;
+
+    let dos = read_at(&mut file, 0, 64)?;
+    if &dos[..2] != b"MZ" {
+        bail!("不是 PE 文件");
+    }
+    let pe_offset = u32_at(&dos, 0x3C) as u64;
+    let header = read_at(&mut file, pe_offset, 24)?;
+    if &header[..4] != b"PE\0\0" {
+        bail!("缺少 PE 签名");
+    }
+
+    let machine = u16_at(&header, 4);
+    let section_count = u16_at(&header, 6) as usize;
+    let timestamp = u32_at(&header, 8);
+    let optional_size = u16_at(&header, 20) as usize;
+    let characteristics = u16_at(&header, 22);
+
+    let optional = read_at(&mut file, pe_offset + 24, optional_size)?;
+    if optional.len() < 70 {
+        bail!("PE 可选头过短");
+    }
+    let is_64bit = match u16_at(&optional, 0) {
+        0x10b => false,
+        0x20b => true,
+        other => bail!("未知的可选头类型 0x{:x}", other),
+    };
+    let subsystem = u16_at(&optional, 68);
+
+    // 数据目录第 2 项是资源表
+    let directories = if is_64bit { 112 } else { 96 };
+    let resource_rva = optional.get(directories + 16..directories + 20).map(|b| u32_at(b, 0)).unwrap_or(0);
+
+    let table = read_at(&mut file, pe_offset + 24 + optional_size as u64, section_count * 40)?;
+    let sections: Vec<Section> = table
+        .chunks_exact(40)
+        .map(|s| Section {
+            name: String::from_utf8_lossy(&s[..8]).trim_end_matches('\0').to_string(),
+            virtual_size: u32_at(s, 8),
+            virtual_address: u32_at(s, 12),
+            raw_size: u32_at(s, 16),
+            raw_offset: u32_at(s, 20),
+        })
+        .collect();
+
+    let version = if resource_rva != 0 {
+        read_version(&mut file, &sections, resource_rva).unwrap_or_else(|e| {
+            tracing::debug!("读取版本资源失败 {}: {}", path.display(), e);
+            None
+        })
+    } else {
+        None
+    };
+
+    Ok(PeInfo {
+        machine: machine_name(machine),
+        subsystem: subsystem_name(subsystem).to_string(),
+        is_64bit,
+        is_dll: characteristics & 0x2000 != 0,
+        timestamp,
+        sections: sections.iter().map(|s| s.name.clone()).collect(),
+        version,
+    })
+}
+
+fn machine_name(machine: u16) -> String {
+    match machine {
+        0x014c => "x86".to_string(),
+        0x8664 => "x64".to_string(),
+        0x01c0 => "ARM".to_string(),
+        0x01c4 => "ARM Thumb-2".to_string(),
+        0xaa64 => "ARM64".to_string(),
+        0x0200 => "IA-64".to_string(),
+        0x5064 => "RISC-V 64".to_string(),
+        other => format!("未知 (0x{:04x})", other),
+    }
+}
+
+fn subsystem_name(subsystem: u16) -> &'static str {
+    match subsystem {
+        1 => "原生（驱动）",
+        2 => "Windows 图形界面",
+        3 => "Windows 控制台",
+        5 => "OS/2 控制台",
+        7 => "POSIX 控制台",
+        9 => "Windows CE 图形界面",
+        10 => "EFI 应用",
+        11 => "EFI 启动服务驱动",
+        12 => "EFI 运行时驱动",
+        13 => "EFI ROM",
+        14 => "Xbox",
+        16 => "Windows 启动应用",
+        _ => "未知",
+    }
+}
+
+// 相对虚拟地址换算为文件偏移；节表来自文件本身，地址与大小都不可信，按 u64 计算避免溢出
+fn rva_to_offset(sections: &[Section], rva: u32) -> Option<u64> {
+    sections.iter().find_map(|s| {
+        let size = s.virtual_size.max(s.raw_size);
+        let delta = rva.checked_sub(s.virtual_address).filter(|&d| d < size)?;
+        Some(s.raw_offset as u64 + delta as u64)
+    })
+}
+
+// 资源树：类型(RT_VERSION) -> 名称 -> 语言 -> 数据项，各层取第一项
+fn read_version(file: &mut File, sections: &[Section], resource_rva: u32) -> Result<Option<VersionInfo>> {
+    let Some(base) = rva_to_offset(sections, resource_rva) else { return Ok(None) };
+
+    let mut directory = 0u32;
+    for level in 0..3 {
+        let header = read_at(file, base + directory as u64, 16)?;
+        let count = u16_at(&header, 12) as usize + u16_at(&header, 14) as usize;
+        let entries = read_at(file, base + directory as u64 + 16, count * 8)?;
+        let entry = entries.chunks_exact(8).find(|e| level > 0 || u32_at(e, 0) == RT_VERSION);
+        let Some(entry) = entry else { return Ok(None) };
+        let target = u32_at(entry, 4);
+        if level < 2 && target & 0x8000_0000 == 0 {
+            return Ok(None);
+        }
+        directory = target & 0x7FFF_FFFF;
+    }
+
+    let data_entry = read_at(file, base + directory as u64, 8)?;
+    let Some(offset) = rva_to_offset(sections, u32_at(&data_entry, 0)) else { return Ok(None) };
+    let data = read_at(file, offset, u32_at(&data_entry, 4).min(MAX_VERSION_RESOURCE) as usize)?;
+
+    let mut info = VersionInfo::default();
+    parse_version_block(&data, 0, &mut info);
+    Ok(Some(info))
+}
+
+/// 解析 VS_VERSIONINFO 的一个块（wLength、wValueLength、wType、szKey、Value、Children），返回块长度
+fn parse_version_block(data: &[u8], start: usize, info: &mut VersionInfo) -> usize {
+    if start + 6 > data.len() {
+        return 0;
+    }
+    let length = u16_at(data, start) as usize;
+    let value_length = u16_at(data, start + 2) as usize;
+    let is_text = u16_at(data, start + 4) == 1;
+    let end = (start + length).min(data.len());
+    if length < 6 {
+        return 0;
+    }
+
+    let (key, after_key) = utf16_string(data, start + 6, end);
+    let value_start = align4(after_key);
+    // 文本值的长度以 WCHAR 计
+    let value_bytes = if is_text { value_length * 2 } else { value_length };
+    let value_end = (value_start + value_bytes).min(end);
+
+    match key.as_str() {
+        "VS_VERSION_INFO" => {
+            if let Some(fixed) = data.get(value_start..value_end).filter(|v| v.len() >= 52) {
+                if u32_at(fixed, 0) == FIXED_FILE_INFO_SIGNATURE {
+                    info.file_version = Some(version_string(u32_at(fixed, 8), u32_at(fixed, 12)));
+                    info.product_version = Some(version_string(u32_at(fixed, 16), u32_at(fixed, 20)));
+                }
+            }
+        }
+        // StringFileInfo / VarFileInfo / 语言代码页（如 080404b0）只有子块
+        "StringFileInfo" | "VarFileInfo" | "Translation" => {}
+        _ if is_text && value_length > 0 && !is_string_table(&key) => {
+            let (value, _) = utf16_string(data, value_start, value_end);
+            info.strings.insert(key, value.trim().to_string());
+        }
+        _ => {}
+    }
+
+    let mut child = align4(value_end.max(value_start));
+    while child + 6 <= end {
+        let child_length = parse_version_block(data, child, info);
+        if child_length == 0 {
+            break;
+        }
+        child = align4(child + child_length);
+    }
+    length
+}
+
+// StringTable 的键是 8 位十六进制的语言与代码页
+fn is_string_table(key: &str) -> bool {
+    key.len() == 8 && key.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn version_string(ms: u32, ls: u32) -> String {
+    format!("{}.{}.{}.{}", ms >> 16, ms & 0xFFFF, ls >> 16, ls & 0xFFFF)
+}
+
+// 读取以 NUL 结尾的 UTF-16LE 字符串，返回字符串与其后的位置
+fn utf16_string(data: &[u8], start: usize, end: usize) -> (String, usize) {
+    let mut units = Vec::new();
+    let mut pos = start;
+    while pos + 2 <= end {
+        let unit = u16_at(data, pos);
+        pos += 2;
+        if unit == 0 {
+            break;
+        }
+        units.push(unit);
+    }
+    (String::from_utf16_lossy(&units), pos)
+}
+
+fn align4(pos: usize) -> usize {
+    (pos + 3) & !3
+}
+
+fn read_at(file: &mut File, offset: u64, len: usize) -> Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buf = vec![0u8; len];
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([bytes[pos], bytes[pos + 1]])
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn utf16z(text: &str) -> Vec<u8> {
+        text.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn pad4(bytes: &mut Vec<u8>) {
+        while !bytes.len().is_multiple_of(4) {
+            bytes.push(0);
+        }
+    }
+
+    // VS_VERSIONINFO 的一个块：wLength、wValueLength、wType、szKey、Value、Children
+    fn block(key: &str, value: &[u8], value_length: u16, is_text: bool, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut b = vec![0u8; 6];
+        b[2..4].copy_from_slice(&value_length.to_le_bytes());
+        b[4..6].copy_from_slice(&(is_text as u16).to_le_bytes());
+        b.extend(utf16z(key));
+        pad4(&mut b);
+        b.extend(value);
+        for child in children {
+            pad4(&mut b);
+            b.extend(child);
+        }
+        let length = b.len() as u16;
+        b[0..2].copy_from_slice(&length.to_le_bytes());
+        b
+    }
+
+    fn text(key: &str, value: &str) -> Vec<u8> {
+        block(key, &utf16z(value), value.encode_utf16().count() as u16 + 1, true, &[])
+    }
+
+    /// 最小的 x64 DLL：一个 `.rsrc` 节，内含版本 1.2.3.4 与 CompanyName/FileDescription
+    pub(crate) fn sample_dll() -> Vec<u8> {
+        let mut fixed = vec![0u8; 52];
+        fixed[0..4].copy_from_slice(&FIXED_FILE_INFO_SIGNATURE.to_le_bytes());
+        for (pos, value) in [(8, (1u32 << 16) | 2), (12, (3 << 16) | 4), (16, 5 << 16), (20, 0)] {
+            fixed[pos..pos + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        let strings = block(
+            "StringFileInfo",
+            &[],
+            0,
+            true,
+            &[block("040904b0", &[], 0, true, &[text("CompanyName", "Acme 公司"), text("FileDescription", "Sample  ")])],
+        );
+        let version = block("VS_VERSION_INFO", &fixed, 52, false, &[strings]);
+
+        // 资源树：类型 -> 名称 -> 语言 -> 数据项，节从 RVA 0x1000 / 文件偏移 0x200 开始
+        let mut rsrc = Vec::new();
+        for (id, target) in [(RT_VERSION, 0x8000_0018u32), (1, 0x8000_0030), (0x409, 0x48)] {
+            let mut dir = vec![0u8; 16];
+            dir[14..16].copy_from_slice(&1u16.to_le_bytes());
+            dir.extend(id.to_le_bytes());
+            dir.extend(target.to_le_bytes());
+            rsrc.extend(dir);
+        }
+        rsrc.extend(0x1058u32.to_le_bytes());
+        rsrc.extend((version.len() as u32).to_le_bytes());
+        rsrc.resize(0x58, 0);
+        rsrc.extend(version);
+
+        let mut image = vec![0u8; 0x200];
+        image[0..2].copy_from_slice(b"MZ");
+        image[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        image[0x40..0x44].copy_from_slice(b"PE\0\0");
+        image[0x44..0x46].copy_from_slice(&0x8664u16.to_le_bytes());
+        image[0x46..0x48].copy_from_slice(&1u16.to_le_bytes());
+        image[0x54..0x56].copy_from_slice(&240u16.to_le_bytes());
+        image[0x56..0x58].copy_from_slice(&0x2022u16.to_le_bytes());
+        let optional = 0x58;
+        image[optional..optional + 2].copy_from_slice(&0x20bu16.to_le_bytes());
+        image[optional + 68..optional + 70].copy_from_slice(&2u16.to_le_bytes());
+        image[optional + 128..optional + 132].copy_from_slice(&0x1000u32.to_le_bytes());
+        let section = optional + 240;
+        image[section..section + 5].copy_from_slice(b".rsrc");
+        for (pos, value) in [(8, rsrc.len() as u32), (12, 0x1000), (16, rsrc.len() as u32), (20, 0x200)] {
+            image[section + pos..section + pos + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        image.extend(rsrc);
+        image
+    }
+
+    pub(crate) fn write_sample(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("starsearch-pe-{}-{}.dll", std::process::id(), name));
+        std::fs::write(&path, sample_dll()).unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_headers_and_version_resource() {
+        let path = write_sample("parse");
+        let info = parse(&path).unwrap();
+        assert_eq!((info.machine.as_str(), info.is_64bit, info.is_dll), ("x64", true, true));
+        assert_eq!(info.subsystem, "Windows 图形界面");
+        assert_eq!(info.sections, [".rsrc"]);
+        let version = info.version.unwrap();
+        assert_eq!(version.file_version.as_deref(), Some("1.2.3.4"));
+        assert_eq!(version.product_version.as_deref(), Some("5.0.0.0"));
+        assert_eq!(version.strings["CompanyName"], "Acme 公司");
+        assert_eq!(version.strings["FileDescription"], "Sample");
+        assert_eq!(version.strings.len(), 2);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn truncated_or_foreign_files_are_errors() {
+        let path = std::env::temp_dir().join(format!("starsearch-pe-{}-short.dll", std::process::id()));
+        std::fs::write(&path, &sample_dll()[..0x100]).unwrap();
+        assert!(parse(&path).is_err());
+        std::fs::write(&path, b"MZ").unwrap();
+        assert!(parse(&path).is_err());
+        std::fs::write(&path, vec![b'x'; 4096]).unwrap();
+        assert!(parse(&path).is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn rva_near_the_top_of_the_address_space_does_not_overflow() {
+        let section = |virtual_address, size, raw_offset| Section { name: String::new(), virtual_address, virtual_size: size, raw_offset, raw_size: 0 };
+        let sections = [section(0xFFFF_F000, 0x2000, 0xFFFF_FF00), section(0x1000, 0x100, 0x400)];
+        assert_eq!(rva_to_offset(&sections, 0xFFFF_FFF0), Some(0xFFFF_FF00 + 0xFF0));
+        assert_eq!(rva_to_offset(&sections, 0x1010), Some(0x410));
+        assert_eq!(rva_to_offset(&sections, 0x1100), None);
+        assert_eq!(rva_to_offset(&sections, 0x10), None);
+    }
+}
@@ -0,0 +1,741 @@
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Style, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+use crate::archive::{self, ArchiveMember};
+use crate::config::{BINARY_EXTENSIONS, MAX_ARCHIVE_PREVIEW_MEMBERS, MAX_FOLDER_PREVIEW_CHILDREN, MAX_HEX_PREVIEW_BYTES, MAX_PREVIEW_BYTES, MAX_PREVIEW_LINES};
+use crate::encoding;
+use crate::pe::{self, PeInfo};
+
+/// 语法与主题定义加载较慢，全局只加载一次
+static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+static THEME_SET: Lazy<ThemeSet> = Lazy::new(ThemeSet::load_defaults);
+
+const DARK_THEME: &str = "base16-ocean.dark";
+const LIGHT_THEME: &str = "InspiredGitHub";
+
+/// 一段同样式的文本
+#[derive(Debug, Clone, Serialize)]
+pub struct StyledSpan {
+    pub text: String,
+    /// 前景色 RGB
+    pub color: [u8; 3],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub bold: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub italic: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub underline: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewLine {
+    /// 行号，从 1 开始
+    pub number: usize,
+    pub spans: Vec<StyledSpan>,
+}
+
+/// 文本文件的高亮预览，不依赖任何 UI，GUI 侧栏与 CLI 共用
+#[derive(Debug, Clone, Serialize)]
+pub struct TextPreview {
+    pub path: String,
+    /// 识别出的语法名称（无法识别时为 "Plain Text"）
+    pub syntax: String,
+    pub encoding: &'static str,
+    /// 主题背景色 RGB
+    pub background: [u8; 3],
+    pub lines: Vec<PreviewLine>,
+    /// 文件超过 `MAX_PREVIEW_BYTES` 或 `MAX_PREVIEW_LINES`，只预览了开头
+    pub truncated: bool,
+}
+
+/// 文件预览：文本高亮或二进制的十六进制与元数据
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Preview {
+    Text(TextPreview),
+    Binary(BinaryPreview),
+    Folder(FolderPreview),
+    Archive(ArchivePreview),
+}
+
+/// 按扩展名与文件头选择预览方式；目录直接读取子项，压缩包列出成员
+pub fn preview_file(path: &Path, dark: bool) -> Result<Preview> {
+    if path.is_dir() {
+        return Ok(Preview::Folder(preview_folder(path, None)?));
+    }
+    if archive::is_archive(path) {
+        return Ok(Preview::Archive(preview_archive(path)?));
+    }
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    let binary = BINARY_EXTENSIONS.contains(&ext.as_str())
+        || encoding::sniff_file(path).map(|(_, binary)| binary).unwrap_or(false);
+    if binary {
+        Ok(Preview::Binary(preview_binary(path)?))
+    } else {
+        Ok(Preview::Text(preview_text(path, dark)?))
+    }
+}
+
+impl Preview {
+    /// 终端输出；文件内容、文件名中的控制字符已替换，不会被终端当作转义序列执行
+    pub fn to_ansi(&self) -> String {
+        match self {
+            Preview::Text(text) => text.to_ansi(),
+            Preview::Binary(binary) => strip_controls(&binary.to_text()),
+            Preview::Folder(folder) => strip_controls(&folder.to_text()),
+            Preview::Archive(archive) => strip_controls(&archive.to_text()),
+        }
+    }
+
+    pub fn to_html(&self) -> String {
+        match self {
+            Preview::Text(text) => text.to_html(),
+            Preview::Binary(binary) => format!("<pre>\n{}</pre>\n", escape_html(&binary.to_text())),
+            Preview::Folder(folder) => format!("<pre>\n{}</pre>\n", escape_html(&folder.to_text())),
+            Preview::Archive(archive) => format!("<pre>\n{}</pre>\n", escape_html(&archive.to_text())),
+        }
+    }
+}
+
+/// 读取文件开头做高亮预览：按扩展名、再按首行（如 `#!/bin/bash`）选择语法
+pub fn preview_text(path: &Path, dark: bool) -> Result<TextPreview> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut bytes = Vec::with_capacity(size.min(MAX_PREVIEW_BYTES) as usize);
+    file.take(MAX_PREVIEW_BYTES).read_to_end(&mut bytes)?;
+
+    let file_encoding = encoding::detect(&bytes);
+    // UTF-16 文本本身含大量 NUL，只有其他编码才按 NUL 判定二进制
+    if !file_encoding.name().starts_with("UTF-16") && bytes.contains(&0) {
+        bail!("二进制文件不支持文本预览");
+    }
+    let (text, _, _) = file_encoding.decode(&bytes);
+
+    let syntax = find_syntax(path, &text);
+    let theme = &THEME_SET.themes[if dark { DARK_THEME } else { LIGHT_THEME }];
+    let background = theme.settings.background.map(|c| [c.r, c.g, c.b]).unwrap_or([0, 0, 0]);
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut lines = Vec::new();
+    let mut truncated = size > MAX_PREVIEW_BYTES;
+    for (i, line) in LinesWithEndings::from(&text).enumerate() {
+        if i >= MAX_PREVIEW_LINES {
+            truncated = true;
+            break;
+        }
+        let ranges = highlighter.highlight_line(line, &SYNTAX_SET)?;
+        let spans = ranges
+            .into_iter()
+            .map(|(style, text)| to_span(style, text.trim_end_matches(['\r', '\n'])))
+            .filter(|span| !span.text.is_empty())
+            .collect();
+        lines.push(PreviewLine { number: i + 1, spans });
+    }
+
+    Ok(TextPreview {
+        path: path.to_string_lossy().to_string(),
+        syntax: syntax.name.clone(),
+        encoding: file_encoding.name(),
+        background,
+        lines,
+        truncated,
+    })
+}
+
+fn find_syntax<'a>(path: &Path, text: &str) -> &'a SyntaxReference {
+    let by_extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(|ext| SYNTAX_SET.find_syntax_by_extension(ext))
+        .or_else(|| {
+            // Makefile、Dockerfile 等没有扩展名，按文件名查
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|name| SYNTAX_SET.find_syntax_by_extension(name))
+        });
+    by_extension
+        .or_else(|| text.lines().next().and_then(|first| SYNTAX_SET.find_syntax_by_first_line(first)))
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text())
+}
+
+fn to_span(style: Style, text: &str) -> StyledSpan {
+    StyledSpan {
+        text: text.to_string(),
+        color: [style.foreground.r, style.foreground.g, style.foreground.b],
+        bold: style.font_style.contains(FontStyle::BOLD),
+        italic: style.font_style.contains(FontStyle::ITALIC),
+        underline: style.font_style.contains(FontStyle::UNDERLINE),
+    }
+}
+
+impl TextPreview {
+    /// 24 位色 ANSI 转义文本，带行号
+    pub fn to_ansi(&self) -> String {
+        let width = self.lines.last().map(|l| l.number.to_string().len()).unwrap_or(1);
+        let mut out = String::new();
+        for line in &self.lines {
+            out.push_str(&format!("\x1b[2m{:>width$} │\x1b[0m ", line.number, width = width));
+            for span in &line.spans {
+                let [r, g, b] = span.color;
+                out.push_str(&format!("\x1b[38;2;{};{};{}m", r, g, b));
+                if span.bold {
+                    out.push_str("\x1b[1m");
+                }
+                if span.italic {
+                    out.push_str("\x1b[3m");
+                }
+                if span.underline {
+                    out.push_str("\x1b[4m");
+                }
+                out.push_str(&strip_controls(&span.text));
+                out.push_str("\x1b[0m");
+            }
+            out.push('\n');
+        }
+        if self.truncated {
+            out.push_str("\x1b[2m…（仅预览开头部分）\x1b[0m\n");
+        }
+        out
+    }
+
+    /// 独立的 HTML 片段（`<pre>`，内联样式）
+    pub fn to_html(&self) -> String {
+        let [r, g, b] = self.background;
+        let mut out = format!("<pre style=\"background-color:#{:02x}{:02x}{:02x};\">\n", r, g, b);
+        for line in &self.lines {
+            out.push_str(&format!("<span style=\"opacity:0.5;\">{:>4} </span>", line.number));
+            for span in &line.spans {
+                let [r, g, b] = span.color;
+                let mut style = format!("color:#{:02x}{:02x}{:02x};", r, g, b);
+                if span.bold {
+                    style.push_str("font-weight:bold;");
+                }
+                if span.italic {
+                    style.push_str("font-style:italic;");
+                }
+                if span.underline {
+                    style.push_str("text-decoration:underline;");
+                }
+                out.push_str(&format!("<span style=\"{}\">{}</span>", style, escape_html(&span.text)));
+            }
+            out.push('\n');
+        }
+        out.push_str("</pre>\n");
+        out
+    }
+}
+
+/// 控制字符（ESC、BEL、CR 等）替换为 U+FFFD，保留换行与制表符
+fn strip_controls(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() && c != '\n' && c != '\t' { '\u{FFFD}' } else { c })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// 二进制文件预览：文件头十六进制、魔数识别的类型、大小与时间，可执行文件附带 PE 头信息
+#[derive(Debug, Clone, Serialize)]
+pub struct BinaryPreview {
+    pub path: String,
+    /// 按魔数识别的文件类型
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<&'static str>,
+    pub size: u64,
+    /// 时间均为 Unix 秒，文件系统不支持时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessed: Option<u64>,
+    pub hex: Vec<HexRow>,
+    /// 只预览了前 `MAX_HEX_PREVIEW_BYTES` 字节
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pe: Option<PeInfo>,
+}
+
+/// 十六进制视图的一行（16 字节）
+#[derive(Debug, Clone, Serialize)]
+pub struct HexRow {
+    pub offset: usize,
+    pub hex: String,
+    pub ascii: String,
+}
+
+/// 魔数表：（偏移，字节，类型）
+const MAGIC: &[(usize, &[u8], &str)] = &[
+    (0, b"MZ", "Windows 可执行文件 (PE)"),
+    (0, b"\x7fELF", "ELF 可执行文件"),
+    (0, b"\xcf\xfa\xed\xfe", "Mach-O 可执行文件"),
+    (0, b"\xca\xfe\xba\xbe", "Java class / Mach-O 通用二进制"),
+    (0, b"\0asm", "WebAssembly 模块"),
+    (0, b"PK\x03\x04", "ZIP 压缩包（含 Office/JAR/APK）"),
+    (0, b"Rar!\x1a\x07", "RAR 压缩包"),
+    (0, b"7z\xbc\xaf\x27\x1c", "7z 压缩包"),
+    (0, b"\x1f\x8b", "gzip 压缩数据"),
+    (0, b"BZh", "bzip2 压缩数据"),
+    (0, b"\xfd7zXZ\0", "xz 压缩数据"),
+    (0, b"\x28\xb5\x2f\xfd", "Zstandard 压缩数据"),
+    (257, b"ustar", "tar 归档"),
+    (0, b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1", "OLE2 复合文档（旧版 Office/MSI）"),
+    (0, b"%PDF", "PDF 文档"),
+    (0, b"SQLite format 3\0", "SQLite 数据库"),
+    (0, b"\x89PNG\r\n\x1a\n", "PNG 图片"),
+    (0, b"\xff\xd8\xff", "JPEG 图片"),
+    (0, b"GIF8", "GIF 图片"),
+    (0, b"BM", "BMP 图片"),
+    (0, b"\0\0\x01\0", "ICO 图标"),
+    (8, b"WEBP", "WebP 图片"),
+    (8, b"WAVE", "WAV 音频"),
+    (8, b"AVI ", "AVI 视频"),
+    (4, b"ftyp", "MP4/MOV 媒体"),
+    (0, b"\x1a\x45\xdf\xa3", "Matroska/WebM 媒体"),
+    (0, b"ID3", "MP3 音频"),
+    (0, b"fLaC", "FLAC 音频"),
+    (0, b"OggS", "Ogg 媒体"),
+    (0, b"MSCF", "CAB 压缩包"),
+    (0, b"ITSF", "CHM 帮助文件"),
+    (0, b"!<arch>\n", "ar 归档 / 静态库"),
+];
+
+/// 按魔数识别文件类型
+pub fn detect_type(header: &[u8]) -> Option<&'static str> {
+    MAGIC
+        .iter()
+        .find(|(offset, magic, _)| header.get(*offset..offset + magic.len()) == Some(*magic))
+        .map(|(_, _, name)| *name)
+}
+
+pub fn preview_binary(path: &Path) -> Result<BinaryPreview> {
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let mut bytes = Vec::with_capacity(MAX_HEX_PREVIEW_BYTES);
+    file.take(MAX_HEX_PREVIEW_BYTES as u64).read_to_end(&mut bytes)?;
+
+    let file_type = detect_type(&bytes);
+    let pe = if bytes.starts_with(b"MZ") { pe::parse(path).ok() } else { None };
+
+    Ok(BinaryPreview {
+        path: path.to_string_lossy().to_string(),
+        file_type,
+        size: metadata.len(),
+        created: unix_secs(metadata.created()),
+        modified: unix_secs(metadata.modified()),
+        accessed: unix_secs(metadata.accessed()),
+        hex: hex_rows(&bytes),
+        truncated: metadata.len() > bytes.len() as u64,
+        pe,
+    })
+}
+
+// 不记录创建时间的文件系统可能返回 0
+fn unix_secs(time: std::io::Result<std::time::SystemTime>) -> Option<u64> {
+    time.ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .filter(|&secs| secs > 0)
+}
+
+fn format_time(secs: u64) -> Option<String> {
+    chrono::DateTime::from_timestamp(secs as i64, 0)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn hex_rows(bytes: &[u8]) -> Vec<HexRow> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut hex = String::with_capacity(49);
+            for (j, b) in chunk.iter().enumerate() {
+                if j == 8 {
+                    hex.push(' ');
+                }
+                hex.push_str(&format!("{:02x} ", b));
+            }
+            HexRow {
+                offset: i * 16,
+                hex: hex.trim_end().to_string(),
+                ascii: chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect(),
+            }
+        })
+        .collect()
+}
+
+impl BinaryPreview {
+    /// 元数据摘要（标签，值），GUI 与纯文本输出共用
+    pub fn summary(&self) -> Vec<(&'static str, String)> {
+        let mut items = vec![
+            ("类型", self.file_type.unwrap_or("未知").to_string()),
+            ("大小", format!("{} 字节", self.size)),
+        ];
+        for (label, time) in [("创建", self.created), ("修改", self.modified), ("访问", self.accessed)] {
+            if let Some(time) = time.and_then(format_time) {
+                items.push((label, time));
+            }
+        }
+        if let Some(pe) = &self.pe {
+            items.push((
+                "PE",
+                format!(
+                    "{} {}{}，{}",
+                    pe.machine,
+                    if pe.is_64bit { "PE32+" } else { "PE32" },
+                    if pe.is_dll { " DLL" } else { "" },
+                    pe.subsystem
+                ),
+            ));
+            if let Some(version) = &pe.version {
+                if let Some(v) = &version.file_version {
+                    items.push(("文件版本", v.clone()));
+                }
+                if let Some(v) = &version.product_version {
+                    items.push(("产品版本", v.clone()));
+                }
+            }
+        }
+        items
+    }
+
+    /// 版本资源中的字符串字段（CompanyName、FileDescription 等），没有版本资源时为空
+    pub fn version_strings(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.pe.iter().filter_map(|pe| pe.version.as_ref()).flat_map(|version| version.strings.iter())
+    }
+
+    /// 纯文本形式：元数据摘要、版本资源字段 + `xxd` 风格的十六进制
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (label, value) in self.summary() {
+            out.push_str(&format!("{}: {}\n", label, value));
+        }
+        for (key, value) in self.version_strings() {
+            out.push_str(&format!("{}: {}\n", key, value));
+        }
+        out.push('\n');
+        for row in &self.hex {
+            out.push_str(&format!("{:08x}  {:<49}  {}\n", row.offset, row.hex, row.ascii));
+        }
+        if self.truncated {
+            out.push_str("…（仅显示开头部分）\n");
+        }
+        out
+    }
+}
+
+/// 目录的直接子项
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderChild {
+    pub name: String,
+    pub is_dir: bool,
+    /// 文件大小；子目录的大小仅在索引提供时有值
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+}
+
+/// 目录预览：直接子项列表与汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderPreview {
+    pub path: String,
+    pub file_count: usize,
+    pub dir_count: usize,
+    /// 直接子文件大小之和，不含子目录（索引给出的目录大小是递归的，读目录时又拿不到，两种来源无法一致）
+    pub total_size: u64,
+    /// 目录在前、按名称排序，最多 `MAX_FOLDER_PREVIEW_CHILDREN` 项
+    pub children: Vec<FolderChild>,
+    /// 最近修改的子项
+    pub recent: Vec<FolderChild>,
+    pub truncated: bool,
+    /// 子项来源：`index`（Everything 索引）或 `filesystem`（直接读取目录）
+    pub source: &'static str,
+}
+
+/// 最近修改列表的长度
+const RECENT_CHILDREN: usize = 5;
+
+/// 汇总目录的直接子项；`indexed` 为索引查出的子项，没有时读取目录（不递归）
+pub fn preview_folder(path: &Path, indexed: Option<Vec<FolderChild>>) -> Result<FolderPreview> {
+    let (mut children, source) = match indexed {
+        Some(children) => (children, "index"),
+        None => (read_children(path)?, "filesystem"),
+    };
+
+    let dir_count = children.iter().filter(|c| c.is_dir).count();
+    let file_count = children.len() - dir_count;
+    let total_size = children.iter().filter(|c| !c.is_dir).map(|c| c.size).sum();
+
+    children.sort_by_key(|c| c.modified.map(std::cmp::Reverse));
+    let recent = children.iter().filter(|c| c.modified.is_some()).take(RECENT_CHILDREN).cloned().collect();
+
+    children.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+    let truncated = children.len() > MAX_FOLDER_PREVIEW_CHILDREN;
+    children.truncate(MAX_FOLDER_PREVIEW_CHILDREN);
+
+    Ok(FolderPreview {
+        path: path.to_string_lossy().to_string(),
+        file_count,
+        dir_count,
+        total_size,
+        children,
+        recent,
+        truncated,
+        source,
+    })
+}
+
+fn read_children(path: &Path) -> Result<Vec<FolderChild>> {
+    Ok(std::fs::read_dir(path)?
+        .filter_map(|e| e.ok())
+        .map(|entry| {
+            let metadata = entry.metadata().ok();
+            let is_dir = metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false);
+            FolderChild {
+                name: entry.file_name().to_string_lossy().to_string(),
+                is_dir,
+                size: metadata.as_ref().filter(|_| !is_dir).map(|m| m.len()).unwrap_or(0),
+                modified: metadata.and_then(|m| unix_secs(m.modified())),
+            }
+        })
+        .collect())
+}
+
+impl FolderPreview {
+    pub fn summary(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("文件", self.file_count.to_string()),
+            ("子目录", self.dir_count.to_string()),
+            ("文件总大小", format!("{} 字节", self.total_size)),
+            ("来源", if self.source == "index" { "索引" } else { "文件系统" }.to_string()),
+        ]
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (label, value) in self.summary() {
+            out.push_str(&format!("{}: {}\n", label, value));
+        }
+        if !self.recent.is_empty() {
+            out.push_str("\n最近修改:\n");
+            for child in &self.recent {
+                let time = child.modified.and_then(format_time).unwrap_or_default();
+                out.push_str(&format!("  {}  {}\n", time, child.name));
+            }
+        }
+        out.push('\n');
+        for child in &self.children {
+            if child.is_dir {
+                out.push_str(&format!("  {}/\n", child.name));
+            } else {
+                out.push_str(&format!("  {:<40} {:>12}\n", child.name, child.size));
+            }
+        }
+        if self.truncated {
+            out.push_str("…（仅显示部分子项）\n");
+        }
+        out
+    }
+}
+
+/// 压缩包预览：成员列表与汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchivePreview {
+    pub path: String,
+    pub file_count: usize,
+    pub dir_count: usize,
+    /// 解压后总大小
+    pub total_size: u64,
+    /// 压缩后总大小；tar 没有逐成员的压缩信息时为 None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed_size: Option<u64>,
+    /// 按包内路径排序，最多 `MAX_ARCHIVE_PREVIEW_MEMBERS` 项
+    pub members: Vec<ArchiveMember>,
+    pub truncated: bool,
+}
+
+/// 列出压缩包成员（zip 中非 UTF-8 的成员名按 GBK 解码）
+pub fn preview_archive(path: &Path) -> Result<ArchivePreview> {
+    let mut members = archive::list_members(path)?;
+
+    let dir_count = members.iter().filter(|m| m.is_dir).count();
+    let file_count = members.len() - dir_count;
+    let files = members.iter().filter(|m| !m.is_dir);
+    let total_size = files.clone().map(|m| m.size).sum();
+    let compressed_size = files.map(|m| m.compressed_size).sum();
+
+    members.sort_by_key(|m| m.path.to_lowercase());
+    let truncated = members.len() > MAX_ARCHIVE_PREVIEW_MEMBERS;
+    members.truncate(MAX_ARCHIVE_PREVIEW_MEMBERS);
+
+    Ok(ArchivePreview {
+        path: path.to_string_lossy().to_string(),
+        file_count,
+        dir_count,
+        total_size,
+        compressed_size,
+        members,
+        truncated,
+    })
+}
+
+/// 压缩率：压缩后占原大小的百分比
+pub fn compression_ratio(size: u64, compressed: Option<u64>) -> Option<String> {
+    compressed.filter(|_| size > 0).map(|c| format!("{:.1}%", c as f64 * 100.0 / size as f64))
+}
+
+impl ArchivePreview {
+    pub fn summary(&self) -> Vec<(&'static str, String)> {
+        let mut items = vec![
+            ("文件", self.file_count.to_string()),
+            ("目录", self.dir_count.to_string()),
+            ("解压后", format!("{} 字节", self.total_size)),
+        ];
+        if let Some(compressed) = self.compressed_size {
+            items.push(("压缩后", format!("{} 字节", compressed)));
+        }
+        if let Some(ratio) = compression_ratio(self.total_size, self.compressed_size) {
+            items.push(("压缩率", ratio));
+        }
+        items
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (label, value) in self.summary() {
+            out.push_str(&format!("{}: {}\n", label, value));
+        }
+        out.push('\n');
+        for member in &self.members {
+            let time = Some(member.modified).filter(|&t| t > 0).and_then(format_time).unwrap_or_default();
+            if member.is_dir {
+                out.push_str(&format!("  {:<19}  {:>12}  {:>5}  {}\\\n", time, "", "", member.path));
+            } else {
+                let ratio = compression_ratio(member.size, member.compressed_size).unwrap_or_default();
+                out.push_str(&format!("  {:<19}  {:>12}  {:>5}  {}\n", time, member.size, ratio, member.path));
+            }
+        }
+        if self.truncated {
+            out.push_str("…（仅显示部分成员）\n");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, content: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("starsearch-preview-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn ansi_output_neutralizes_escape_sequences() {
+        let path = temp_file("evil.txt", b"title \x1b]0;pwned\x07 \x1b[2J\x9b\tok\r\nnext\n");
+        let ansi = preview_file(&path, true).unwrap().to_ansi();
+        assert!(!ansi.contains("\x1b]") && !ansi.contains("\x1b[2J") && !ansi.contains('\x07') && !ansi.contains('\u{9b}'));
+        assert!(ansi.contains("\u{FFFD}]0;pwned\u{FFFD}") && ansi.contains("\tok") && ansi.contains("next"));
+        std::fs::remove_file(&path).ok();
+
+        let folder = FolderPreview {
+            path: String::new(),
+            file_count: 1,
+            dir_count: 0,
+            total_size: 0,
+            children: vec![FolderChild { name: "a\x1b[31mb".to_string(), is_dir: false, size: 0, modified: None }],
+            recent: Vec::new(),
+            truncated: false,
+            source: "filesystem",
+        };
+        assert!(!Preview::Folder(folder).to_ansi().contains('\x1b'));
+    }
+
+    #[test]
+    fn pe_preview_lists_version_fields() {
+        let path = crate::pe::tests::write_sample("preview");
+        let preview = preview_binary(&path).unwrap();
+        assert_eq!(preview.file_type, Some("Windows 可执行文件 (PE)"));
+        let summary = preview.summary();
+        assert!(summary.contains(&("PE", "x64 PE32+ DLL，Windows 图形界面".to_string())));
+        assert!(summary.contains(&("文件版本", "1.2.3.4".to_string())));
+        let strings: Vec<_> = preview.version_strings().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(strings, [("CompanyName", "Acme 公司"), ("FileDescription", "Sample")]);
+        assert!(preview.to_text().contains("CompanyName: Acme 公司\n"));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn archive_preview_sums_sizes_and_ratio() {
+        let path = std::env::temp_dir().join(format!("starsearch-preview-{}-a.zip", std::process::id()));
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let deflate = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.add_directory("docs/", deflate).unwrap();
+        zip.start_file("docs/b.txt", deflate).unwrap();
+        std::io::Write::write_all(&mut zip, &[b'a'; 1000]).unwrap();
+        zip.start_file("A.txt", deflate).unwrap();
+        std::io::Write::write_all(&mut zip, b"hello").unwrap();
+        zip.finish().unwrap();
+
+        let preview = preview_archive(&path).unwrap();
+        assert_eq!((preview.file_count, preview.dir_count, preview.total_size), (2, 1, 1005));
+        let compressed = preview.compressed_size.unwrap();
+        assert!(compressed < 1005);
+        let names: Vec<_> = preview.members.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(names, ["A.txt", "docs", "docs\\b.txt"]);
+        assert!(preview.summary().contains(&("压缩率", compression_ratio(1005, Some(compressed)).unwrap())));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn compression_ratio_handles_empty_and_unknown() {
+        assert_eq!(compression_ratio(200, Some(50)).as_deref(), Some("25.0%"));
+        assert_eq!(compression_ratio(0, Some(10)), None);
+        assert_eq!(compression_ratio(100, None), None);
+    }
+
+    #[test]
+    fn folder_preview_orders_and_summarizes_children() {
+        let child = |name: &str, is_dir: bool, size: u64, modified: Option<u64>| FolderChild { name: name.to_string(), is_dir, size, modified };
+        let indexed = vec![
+            child("b.txt", false, 10, Some(300)),
+            child("Zeta", true, 500, Some(100)),
+            child("a.txt", false, 5, None),
+            child("alpha", true, 0, Some(200)),
+        ];
+        let preview = preview_folder(Path::new("/data"), Some(indexed)).unwrap();
+        assert_eq!((preview.file_count, preview.dir_count, preview.total_size, preview.source), (2, 2, 15, "index"));
+        let names: Vec<_> = preview.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["alpha", "Zeta", "a.txt", "b.txt"]);
+        let recent: Vec<_> = preview.recent.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(recent, ["b.txt", "alpha", "Zeta"]);
+
+        let many = (0..MAX_FOLDER_PREVIEW_CHILDREN + 1).map(|i| child(&format!("{}.txt", i), false, 1, None)).collect();
+        let preview = preview_folder(Path::new("/data"), Some(many)).unwrap();
+        assert!(preview.truncated && preview.children.len() == MAX_FOLDER_PREVIEW_CHILDREN);
+        assert_eq!(preview.file_count, MAX_FOLDER_PREVIEW_CHILDREN + 1);
+    }
+
+    #[test]
+    fn folder_preview_reads_the_filesystem_without_an_index() {
+        let dir = std::env::temp_dir().join(format!("starsearch-preview-{}-folder", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("f.txt"), b"12345").unwrap();
+        let preview = preview_folder(&dir, None).unwrap();
+        assert_eq!((preview.file_count, preview.dir_count, preview.total_size, preview.source), (1, 1, 5, "filesystem"));
+        assert_eq!(preview.children[0].name, "sub");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
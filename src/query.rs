@@ -0,0 +1,164 @@
+use crate::types::{EntryKind, FileEntry};
+
+/// 本地索引查询：从原始查询中剥离 `deleted:`、`ext:` 等修饰符，其余部分作为关键词
+#[derive(Debug, Clone, Default)]
+pub struct LocalQuery {
+    pub keyword: String,
+    /// `deleted:` 仅返回从 MFT 未使用记录中恢复的已删除文件
+    pub deleted: bool,
+    /// `ads:` 仅返回备用数据流（`file:stream`）
+    pub ads: bool,
+    /// `ext:rs;toml` 限定扩展名（小写、不含点）
+    pub extensions: Vec<String>,
+    /// `path:src` 路径须包含的片段（小写），可出现多次
+    pub paths: Vec<String>,
+    /// `content:"TODO"` 文件内容须匹配的查询，只在文件名部分筛出的文件中搜索
+    pub content: Option<String>,
+}
+
+impl LocalQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        let mut terms = Vec::new();
+
+        for token in split_terms(query) {
+            let lower = token.to_lowercase();
+            match lower.as_str() {
+                "deleted:" => parsed.deleted = true,
+                "ads:" => parsed.ads = true,
+                _ if lower.starts_with("ext:") => parsed.extensions.extend(
+                    lower[4..]
+                        .split([';', ','])
+                        .map(|e| e.trim_start_matches('.').to_string())
+                        .filter(|e| !e.is_empty()),
+                ),
+                _ if lower.starts_with("path:") && lower.len() > 5 => parsed.paths.push(lower[5..].to_string()),
+                _ if lower.starts_with("content:") && token.len() > 8 => parsed.content = Some(token[8..].to_string()),
+                _ => terms.push(token),
+            }
+        }
+
+        parsed.keyword = terms.join(" ");
+        parsed
+    }
+
+    /// 条目类型是否在本次查询范围内；已删除条目和数据流只在对应修饰符下出现，避免干扰日常搜索
+    pub fn accepts(&self, kind: EntryKind) -> bool {
+        if self.deleted {
+            kind == EntryKind::Deleted
+        } else if self.ads {
+            kind == EntryKind::Stream
+        } else {
+            matches!(kind, EntryKind::Normal | EntryKind::ArchiveMember)
+        }
+    }
+
+    /// `ext:` / `path:` 过滤；指定扩展名时目录不参与匹配
+    pub fn matches_filters(&self, entry: &FileEntry) -> bool {
+        if !self.extensions.is_empty() && (entry.is_dir || !self.extensions.contains(&entry.extension)) {
+            return false;
+        }
+        if self.paths.is_empty() {
+            return true;
+        }
+        let path = entry.path.to_lowercase();
+        self.paths.iter().all(|p| path.contains(p.as_str()))
+    }
+
+    /// 是否含有文件名部分（关键词或 `ext:` / `path:` 过滤）
+    pub fn has_filename_part(&self) -> bool {
+        !self.keyword.is_empty() || !self.extensions.is_empty() || !self.paths.is_empty()
+    }
+}
+
+/// 按空白切分，双引号内的空白不切分，引号本身去掉：`content:"a b"` -> `content:a b`
+fn split_terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, is_dir: bool) -> FileEntry {
+        let name = path.rsplit('\\').next().unwrap_or(path).to_string();
+        let extension = if is_dir { String::new() } else { name.rsplit_once('.').map(|(_, e)| e.to_lowercase()).unwrap_or_default() };
+        FileEntry { name, path: path.to_string(), extension, is_dir, ..Default::default() }
+    }
+
+    #[test]
+    fn split_terms_keeps_quoted_spaces() {
+        assert_eq!(split_terms("  foo   bar "), vec!["foo", "bar"]);
+        assert_eq!(split_terms(r#"content:"a b" ext:rs"#), vec!["content:a b", "ext:rs"]);
+        assert_eq!(split_terms(r#""my file".txt"#), vec!["my file.txt"]);
+        // 未闭合的引号一直延续到结尾
+        assert_eq!(split_terms(r#"x "a b"#), vec!["x", "a b"]);
+    }
+
+    #[test]
+    fn parse_strips_modifiers() {
+        let q = LocalQuery::parse(r#"Report ext:.RS;toml,md path:Src deleted: content:"fn main" 2024"#);
+        assert_eq!(q.keyword, "Report 2024");
+        assert_eq!(q.extensions, vec!["rs", "toml", "md"]);
+        assert_eq!(q.paths, vec!["src"]);
+        assert_eq!(q.content.as_deref(), Some("fn main"));
+        assert!(q.deleted && !q.ads);
+
+        // 空修饰符不生效，`path:` 单独出现时当作关键词
+        let q = LocalQuery::parse("ext: path: content: ADS:");
+        assert!(q.extensions.is_empty() && q.paths.is_empty() && q.content.is_none());
+        assert!(q.ads);
+        assert_eq!(q.keyword, "path: content:");
+        assert!(q.has_filename_part());
+        assert!(!LocalQuery::parse(r#"content:"TODO""#).has_filename_part());
+    }
+
+    #[test]
+    fn accepts_only_the_requested_kinds() {
+        let normal = LocalQuery::parse("a");
+        assert!(normal.accepts(EntryKind::Normal) && normal.accepts(EntryKind::ArchiveMember));
+        assert!(!normal.accepts(EntryKind::Deleted) && !normal.accepts(EntryKind::Stream));
+
+        let deleted = LocalQuery::parse("deleted: a");
+        assert!(deleted.accepts(EntryKind::Deleted));
+        assert!(!deleted.accepts(EntryKind::Normal) && !deleted.accepts(EntryKind::Stream));
+
+        let ads = LocalQuery::parse("ads: a");
+        assert!(ads.accepts(EntryKind::Stream));
+        assert!(!ads.accepts(EntryKind::Normal) && !ads.accepts(EntryKind::Deleted));
+    }
+
+    #[test]
+    fn filters_match_extension_and_path() {
+        let q = LocalQuery::parse("ext:rs path:src");
+        assert!(q.matches_filters(&entry(r"C:\proj\src\main.rs", false)));
+        assert!(q.matches_filters(&entry(r"C:\Proj\SRC\Lib.RS", false)));
+        assert!(!q.matches_filters(&entry(r"C:\proj\docs\main.rs", false)));
+        assert!(!q.matches_filters(&entry(r"C:\proj\src\main.toml", false)));
+        // 指定扩展名时目录不参与匹配
+        assert!(!q.matches_filters(&entry(r"C:\proj\src\x.rs", true)));
+
+        let q = LocalQuery::parse("path:proj path:src");
+        assert!(q.matches_filters(&entry(r"C:\proj\src", true)));
+        assert!(!q.matches_filters(&entry(r"C:\proj\docs", true)));
+        assert!(LocalQuery::parse("a").matches_filters(&entry(r"C:\any", true)));
+    }
+}
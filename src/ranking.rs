@@ -0,0 +1,265 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::content_search::{ContentMatch, MatchOffset};
+
+/// BM25 词频饱和参数
+const K1: f32 = 1.2;
+/// BM25 文件长度归一化强度
+const B: f32 = 0.75;
+/// 文件名命中匹配词时的加成
+const FILENAME_BOOST: f32 = 0.5;
+/// 仅目录路径命中时的加成
+const PATH_BOOST: f32 = 0.2;
+/// 刚修改的文件的最大加成，按半衰期衰减
+const RECENCY_BOOST: f32 = 0.3;
+const RECENCY_HALF_LIFE_DAYS: f32 = 30.0;
+/// 同一文件内，匹配覆盖率高的行排在前面
+const LINE_WEIGHT: f32 = 0.1;
+
+/// 匹配度明细（调试用）：`total = bm25 × (1 + filename + path + recency) × (1 + 0.1 × line)`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScoreBreakdown {
+    /// 文件级 BM25 得分
+    pub bm25: f32,
+    /// 文件内匹配总次数
+    pub term_frequency: u32,
+    /// 各匹配词 IDF 之和（在本次命中的文件集合内计算）
+    pub idf: f32,
+    /// 文件长度与平均长度之比
+    pub length_ratio: f32,
+    pub filename: f32,
+    pub path: f32,
+    pub recency: f32,
+    /// 行内匹配字符占比
+    pub line: f32,
+    pub total: f32,
+}
+
+// 单个文件的统计
+struct FileStats {
+    term_freq: HashMap<String, u32>,
+    length: f32,
+    modified: Option<SystemTime>,
+}
+
+/// 按 BM25 为全部匹配重新打分：词频按文件统计，稀有度按命中文件集合计算，
+/// 再叠加文件名/路径命中与最近修改加成。`detail` 为 true 时附带明细。
+///
+/// 词频只从传入的匹配中统计，而每个文件的匹配数受 `max_per_file` 限制，
+/// 命中次数超过上限的文件词频按上限计
+pub fn rank(matches: &mut [ContentMatch], query: &str, detail: bool) {
+    let query_lower = query.to_lowercase();
+
+    let mut files: HashMap<String, FileStats> = HashMap::new();
+    for m in matches.iter() {
+        let stats = files.entry(m.full_path.clone()).or_insert_with(|| {
+            let metadata = std::fs::metadata(&m.full_path).ok();
+            FileStats {
+                term_freq: HashMap::new(),
+                length: metadata.as_ref().map(|md| md.len().max(1) as f32).unwrap_or(1.0),
+                modified: metadata.and_then(|md| md.modified().ok()),
+            }
+        });
+        for term in match_terms(&m.line_content, &m.offsets, &query_lower) {
+            *stats.term_freq.entry(term).or_insert(0) += 1;
+        }
+    }
+    if files.is_empty() {
+        return;
+    }
+
+    let file_count = files.len() as f32;
+    let avg_length = files.values().map(|f| f.length).sum::<f32>() / file_count;
+    let mut doc_freq: HashMap<&str, u32> = HashMap::new();
+    for stats in files.values() {
+        for term in stats.term_freq.keys() {
+            *doc_freq.entry(term.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    let now = SystemTime::now();
+    let file_scores: HashMap<&str, ScoreBreakdown> = files
+        .iter()
+        .map(|(path, stats)| {
+            let length_ratio = stats.length / avg_length;
+            let norm = K1 * (1.0 - B + B * length_ratio);
+            let mut breakdown = ScoreBreakdown { length_ratio, ..Default::default() };
+            for (term, &tf) in &stats.term_freq {
+                let df = doc_freq.get(term.as_str()).copied().unwrap_or(1) as f32;
+                let idf = (1.0 + (file_count - df + 0.5) / (df + 0.5)).ln();
+                let tf = tf as f32;
+                breakdown.bm25 += idf * tf * (K1 + 1.0) / (tf + norm);
+                breakdown.idf += idf;
+                breakdown.term_frequency += tf as u32;
+            }
+
+            let (name, dir) = split_path(path);
+            let hits = |text: &str| stats.term_freq.keys().any(|t| text.contains(t.as_str()));
+            if hits(&name) {
+                breakdown.filename = FILENAME_BOOST;
+            } else if hits(&dir) {
+                breakdown.path = PATH_BOOST;
+            }
+
+            if let Some(age) = stats.modified.and_then(|t| now.duration_since(t).ok()) {
+                let days = age.as_secs_f32() / 86_400.0;
+                breakdown.recency = RECENCY_BOOST * 0.5f32.powf(days / RECENCY_HALF_LIFE_DAYS);
+            }
+            (path.as_str(), breakdown)
+        })
+        .collect();
+
+    for m in matches.iter_mut() {
+        let Some(file) = file_scores.get(m.full_path.as_str()) else { continue };
+        let line = line_coverage(&m.line_content, &m.offsets);
+        let total = file.bm25 * (1.0 + file.filename + file.path + file.recency) * (1.0 + LINE_WEIGHT * line);
+        m.score = total;
+        if detail {
+            m.score_detail = Some(ScoreBreakdown { line, total, ..file.clone() });
+        }
+    }
+}
+
+/// 行内匹配字符占比（按字符而非字节计算，中文与英文一视同仁），用于流式结果的初步打分
+pub fn line_coverage(line: &str, offsets: &[MatchOffset]) -> f32 {
+    let total = line.chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+    let matched: usize = offsets
+        .iter()
+        .filter_map(|o| line.get(o.start..o.end))
+        .map(|s| s.chars().count())
+        .sum();
+    (matched as f32 / total as f32).min(1.0)
+}
+
+// 匹配词取实际命中的文本（正则查询也能统计词频）；跨行匹配没有偏移时退回查询本身
+fn match_terms(line: &str, offsets: &[MatchOffset], query_lower: &str) -> Vec<String> {
+    let terms: Vec<String> = offsets
+        .iter()
+        .filter_map(|o| line.get(o.start..o.end))
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_lowercase())
+        .collect();
+    if terms.is_empty() {
+        vec![query_lower.to_string()]
+    } else {
+        terms
+    }
+}
+
+// 拆分为小写的文件名与所在目录
+fn split_path(full_path: &str) -> (String, String) {
+    let path = Path::new(full_path);
+    let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let dir = path.parent().map(|p| p.to_string_lossy().to_lowercase()).unwrap_or_default();
+    (name, dir)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("starsearch-ranking-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // 写入等长文件，返回一条命中 `term` 的匹配
+    fn hit(path: &Path, term: &str) -> ContentMatch {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, format!("{:<64}", term)).unwrap();
+        ContentMatch {
+            full_path: path.to_string_lossy().to_string(),
+            line_number: 1,
+            line_content: format!("x {} y", term),
+            score: 0.0,
+            score_detail: None,
+            locator: None,
+            page: None,
+            encoding: None,
+            offsets: vec![MatchOffset { start: 2, end: 2 + term.len(), column: 3 }],
+            context_before: Vec::new(),
+            context_after: Vec::new(),
+        }
+    }
+
+    fn score_of(matches: &[ContentMatch], path: &Path) -> f32 {
+        let path = path.to_string_lossy();
+        matches.iter().find(|m| m.full_path == path).unwrap().score
+    }
+
+    fn age(path: &Path, days: u64) {
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(days * 86_400)).unwrap();
+    }
+
+    #[test]
+    fn rare_terms_outrank_common_ones() {
+        let dir = temp_dir("idf");
+        let rare = dir.join("f0.txt");
+        let mut matches = vec![hit(&rare, "zebra")];
+        for i in 1..4 {
+            matches.push(hit(&dir.join(format!("f{}.txt", i)), "horse"));
+        }
+        rank(&mut matches, "zebra|horse", true);
+        assert!(score_of(&matches, &rare) > score_of(&matches, &dir.join("f1.txt")));
+        let detail = matches[0].score_detail.as_ref().unwrap();
+        assert_eq!(detail.term_frequency, 1);
+        assert!(detail.idf > matches[1].score_detail.as_ref().unwrap().idf);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn filename_beats_path_beats_content() {
+        let dir = temp_dir("boost");
+        let named = dir.join("other").join("zebra.txt");
+        let in_dir = dir.join("zebra").join("a.txt");
+        let plain = dir.join("other").join("b.txt");
+        let mut matches = vec![hit(&plain, "zebra"), hit(&in_dir, "zebra"), hit(&named, "zebra")];
+        rank(&mut matches, "zebra", true);
+        assert!(score_of(&matches, &named) > score_of(&matches, &in_dir));
+        assert!(score_of(&matches, &in_dir) > score_of(&matches, &plain));
+        let detail = matches[2].score_detail.as_ref().unwrap();
+        assert_eq!((detail.filename, detail.path), (FILENAME_BOOST, 0.0));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn recency_only_breaks_ties() {
+        let dir = temp_dir("recency");
+        let fresh = dir.join("f1.txt");
+        let stale = dir.join("f2.txt");
+        let old_rare = dir.join("f0.txt");
+        let mut matches = vec![hit(&old_rare, "zebra"), hit(&fresh, "horse"), hit(&stale, "horse"), hit(&dir.join("f3.txt"), "horse")];
+        age(&stale, 365);
+        age(&old_rare, 365);
+        rank(&mut matches, "zebra|horse", false);
+        // 内容得分相同时新文件在前
+        assert!(score_of(&matches, &fresh) > score_of(&matches, &stale));
+        // 稀有词的得分差距不会被修改时间抹平
+        assert!(score_of(&matches, &old_rare) > score_of(&matches, &fresh));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn line_coverage_counts_characters() {
+        let offsets = |ranges: &[(usize, usize)]| -> Vec<MatchOffset> {
+            ranges.iter().map(|&(start, end)| MatchOffset { start, end, column: 0 }).collect()
+        };
+        assert_eq!(line_coverage("", &offsets(&[])), 0.0);
+        assert_eq!(line_coverage("abcd", &offsets(&[(0, 2)])), 0.5);
+        // "中文ab"：前两个字符各占 3 字节
+        assert_eq!(line_coverage("中文ab", &offsets(&[(0, 6)])), 0.5);
+        assert_eq!(line_coverage("abcd", &offsets(&[(0, 4), (0, 4)])), 1.0);
+    }
+}
@@ -0,0 +1,334 @@
+use anyhow::{bail, Context, Result};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use grep_matcher::{Captures, Matcher};
+use grep_regex::RegexMatcher;
+use serde::Serialize;
+use similar::TextDiff;
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::{self, RuntimeConfig};
+use crate::content_search::{ContentSearcher, SkipReason, SkippedFile};
+use crate::encoding;
+
+/// 单个文件的改动
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    /// 原文件编码，写回时保持不变
+    pub encoding: &'static str,
+    /// 原文件换行符（`CRLF` / `LF`），替换文本中的换行按此写回
+    pub line_ending: &'static str,
+    pub replacements: usize,
+    /// unified diff
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplaceResult {
+    pub changes: Vec<FileChange>,
+    pub total_replacements: usize,
+    /// false 表示仅预览（dry-run）
+    pub applied: bool,
+    /// 本次替换的原文件备份目录
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_dir: Option<String>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+/// 备份清单中的一项，用于手工还原
+#[derive(Debug, Serialize)]
+struct BackupEntry {
+    original: String,
+    backup: String,
+}
+
+// 待写回的文件
+struct Pending {
+    path: PathBuf,
+    bytes: Vec<u8>,
+    modified: Option<SystemTime>,
+}
+
+/// 跨文件搜索替换：先用内容搜索找出匹配文件（沿用其范围、扩展名与二进制过滤），
+/// 再逐个文件按相同的匹配规则替换。默认只生成 diff，`with_apply(true)` 才写回
+pub struct Replacer {
+    searcher: ContentSearcher,
+    replacement: String,
+    apply: bool,
+}
+
+impl Replacer {
+    /// `replacement` 支持 `$1`、`${name}` 引用捕获组，`$$` 表示 `$`
+    pub fn new(searcher: ContentSearcher, replacement: &str) -> Self {
+        Self { searcher, replacement: replacement.to_string(), apply: false }
+    }
+
+    pub fn with_apply(mut self, apply: bool) -> Self {
+        self.apply = apply;
+        self
+    }
+
+    pub fn run(&self, pattern: &str, rt_config: &RuntimeConfig) -> Result<ReplaceResult> {
+        let matcher = self.searcher.build_matcher(pattern)?;
+
+        // 只需要文件列表，范围内每个匹配的文件都要处理，不受结果条数限制
+        let found = self.searcher.matching_files(pattern, rt_config)?;
+
+        let mut result = ReplaceResult { skipped: found.skipped, ..Default::default() };
+        let mut paths = BTreeSet::new();
+        let mut documents = BTreeSet::new();
+        for m in &found.matches {
+            if m.locator.is_some() || m.page.is_some() {
+                documents.insert(m.full_path.clone());
+            } else {
+                paths.insert(m.full_path.clone());
+            }
+        }
+        result.skipped.extend(documents.into_iter().map(|path| SkippedFile {
+            path,
+            reason: SkipReason::ReadOnlyFormat,
+            message: SkipReason::ReadOnlyFormat.to_string(),
+        }));
+
+        let mut pending = Vec::new();
+        for path in paths {
+            match self.replace_file(&matcher, Path::new(&path)) {
+                Ok(Some((change, write))) => {
+                    result.total_replacements += change.replacements;
+                    result.changes.push(change);
+                    pending.push(write);
+                }
+                Ok(None) => {}
+                Err(message) => result.skipped.push(SkippedFile { path, reason: SkipReason::Undecodable, message }),
+            }
+        }
+
+        if self.apply && !pending.is_empty() {
+            let dir = write_all(&pending)?;
+            result.backup_dir = Some(dir.to_string_lossy().to_string());
+            result.applied = true;
+        }
+        Ok(result)
+    }
+
+    // 解码、替换、按原编码重新编码；没有实际改动时返回 None
+    fn replace_file(&self, matcher: &RegexMatcher, path: &Path) -> std::result::Result<Option<(FileChange, Pending)>, String> {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+
+        let (file_encoding, bom_len) = Encoding::for_bom(&bytes).unwrap_or_else(|| (encoding::detect(&bytes), 0));
+        let (text, had_errors) = file_encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        if had_errors {
+            return Err(format!("按 {} 解码时有无效字节", file_encoding.name()));
+        }
+        let crlf = text.contains("\r\n");
+
+        let haystack = text.as_bytes();
+        let mut caps = matcher.new_captures().map_err(|e| e.to_string())?;
+        let mut replaced = Vec::with_capacity(haystack.len());
+        let mut count = 0;
+        matcher
+            .replace_with_captures(haystack, &mut caps, &mut replaced, |caps, dst| {
+                count += 1;
+                let start = dst.len();
+                caps.interpolate(|name| matcher.capture_index(name), haystack, self.replacement.as_bytes(), dst);
+                if crlf {
+                    let inserted = dst.split_off(start);
+                    dst.extend(to_crlf(&inserted));
+                }
+                true
+            })
+            .map_err(|e| e.to_string())?;
+        if count == 0 {
+            return Ok(None);
+        }
+
+        let new_text = String::from_utf8(replaced).map_err(|_| "替换结果不是有效文本".to_string())?;
+        if new_text == text {
+            return Ok(None);
+        }
+        let new_bytes = encode(&new_text, file_encoding, &bytes[..bom_len])?;
+
+        let display = path.to_string_lossy().to_string();
+        let diff = TextDiff::from_lines(text.as_ref(), new_text.as_str())
+            .unified_diff()
+            .context_radius(3)
+            .header(&display, &display)
+            .to_string();
+
+        Ok(Some((
+            FileChange {
+                path: display,
+                encoding: file_encoding.name(),
+                line_ending: if crlf { "CRLF" } else { "LF" },
+                replacements: count,
+                diff,
+            },
+            Pending { path: path.to_path_buf(), bytes: new_bytes, modified },
+        )))
+    }
+}
+
+// 替换文本中的单独 `\n` 统一为 `\r\n`
+fn to_crlf(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'\n' && (i == 0 || bytes[i - 1] != b'\r') {
+            out.push(b'\r');
+        }
+        out.push(b);
+    }
+    out
+}
+
+// encoding_rs 的 UTF-16 编码器输出 UTF-8，UTF-16 需要手工编码
+fn encode(text: &str, file_encoding: &'static Encoding, bom: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    let mut out = bom.to_vec();
+    if file_encoding == UTF_16LE {
+        out.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    } else if file_encoding == UTF_16BE {
+        out.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    } else {
+        let (bytes, _, had_errors) = file_encoding.encode(text);
+        if had_errors {
+            return Err(format!("替换后的文本无法用 {} 编码", file_encoding.name()));
+        }
+        out.extend_from_slice(&bytes);
+    }
+    Ok(out)
+}
+
+/// 写回全部改动：先备份原文件并在同目录写好临时文件，全部成功后再逐个重命名覆盖；
+/// 任一步失败都会清理临时文件，已覆盖的文件从备份还原。返回备份目录
+fn write_all(pending: &[Pending]) -> Result<PathBuf> {
+    let dir = config::backup_dir().join(chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string());
+    std::fs::create_dir_all(&dir).with_context(|| format!("无法创建备份目录 {}", dir.display()))?;
+
+    let mut manifest = Vec::with_capacity(pending.len());
+    let mut temps: Vec<PathBuf> = Vec::with_capacity(pending.len());
+    let staged = (|| -> Result<()> {
+        for item in pending {
+            // 读取之后文件又被修改，放弃整批替换
+            let current = std::fs::metadata(&item.path).and_then(|m| m.modified()).ok();
+            if current != item.modified {
+                bail!("{} 在预览后被修改，已取消替换", item.path.display());
+            }
+
+            let backup = dir.join(backup_relative(&item.path));
+            if let Some(parent) = backup.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(&item.path, &backup).with_context(|| format!("备份 {} 失败", item.path.display()))?;
+            manifest.push(BackupEntry {
+                original: item.path.to_string_lossy().to_string(),
+                backup: backup.to_string_lossy().to_string(),
+            });
+
+            let temp = temp_path(&item.path);
+            std::fs::write(&temp, &item.bytes).with_context(|| format!("写入 {} 失败", temp.display()))?;
+            temps.push(temp.clone());
+            if let Ok(meta) = std::fs::metadata(&item.path) {
+                std::fs::set_permissions(&temp, meta.permissions()).ok();
+            }
+        }
+        Ok(())
+    })();
+    std::fs::write(dir.join("manifest.json"), serde_json::to_string_pretty(&manifest)?).ok();
+
+    if let Err(e) = staged {
+        for temp in &temps {
+            std::fs::remove_file(temp).ok();
+        }
+        return Err(e);
+    }
+
+    for (i, (item, temp)) in pending.iter().zip(&temps).enumerate() {
+        if let Err(e) = std::fs::rename(temp, &item.path) {
+            for temp in &temps[i..] {
+                std::fs::remove_file(temp).ok();
+            }
+            for (done, entry) in pending[..i].iter().zip(&manifest) {
+                std::fs::copy(&entry.backup, &done.path).ok();
+            }
+            return Err(e).with_context(|| format!("覆盖 {} 失败，已还原此前的改动", item.path.display()));
+        }
+    }
+    Ok(dir)
+}
+
+// 备份路径保留原目录结构：`C:\a\b.txt` -> `C\a\b.txt`
+fn backup_relative(path: &Path) -> PathBuf {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => {
+                relative.push(prefix.as_os_str().to_string_lossy().replace([':', '\\', '?'], ""));
+            }
+            Component::Normal(part) => relative.push(part),
+            _ => {}
+        }
+    }
+    relative
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!(".{}.starsearch-tmp", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 每个测试用独立的临时目录
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("starsearch-replace-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn scope(dir: &Path) -> RuntimeConfig {
+        RuntimeConfig {
+            search_scope: dir.to_string_lossy().to_string(),
+            is_content_search: true,
+            max_results: 10,
+            include_archives: false,
+        }
+    }
+
+    #[test]
+    fn replaces_every_matching_file() {
+        let dir = temp_dir("all");
+        for i in 0..31 {
+            std::fs::write(dir.join(format!("{}.txt", i)), "needle\n".repeat(12)).unwrap();
+        }
+        let result = Replacer::new(ContentSearcher::new(), "pin").run("needle", &scope(&dir)).unwrap();
+        assert_eq!(result.changes.len(), 31);
+        assert_eq!(result.total_replacements, 31 * 12);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn line_anchors_apply_to_every_line() {
+        let dir = temp_dir("anchor");
+        let file = dir.join("a.txt");
+        std::fs::write(&file, "foo 1\nfoo 2\r\nbar foo\n").unwrap();
+        let result = Replacer::new(ContentSearcher::new(), "baz").with_apply(true).run("^foo", &scope(&dir)).unwrap();
+        assert_eq!(result.total_replacements, 2);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "baz 1\nbaz 2\r\nbar foo\n");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn crlf_is_kept_in_inserted_text() {
+        assert_eq!(to_crlf(b"a\nb\r\nc"), b"a\r\nb\r\nc");
+    }
+
+    #[test]
+    fn backup_path_drops_root() {
+        assert_eq!(backup_relative(Path::new("/a/b.txt")), PathBuf::from("a/b.txt"));
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Mutex;
 use rayon::prelude::*;
@@ -264,7 +266,7 @@ impl SearchBackend {
                     }
                 });
                 
-                entries.sort_by(|a, b| b.score.cmp(&a.score));
+                entries.sort_by_key(|e| std::cmp::Reverse(e.score));
 
                 // 3. 更新缓存
                 {
@@ -283,6 +285,30 @@ impl SearchBackend {
         Vec::new()
     }
 
+    /// 从 Everything 索引列出目录的直接子项（`parent:` 查询），索引不可用时返回 None
+    pub fn list_children(&self, dir: &Path) -> Option<Vec<crate::preview::FolderChild>> {
+        let es_path = self.es_path.as_ref()?;
+        // parent: 需要不带结尾分隔符的完整路径，盘符根目录除外
+        let dir_str = dir.to_string_lossy();
+        let trimmed = dir_str.trim_end_matches(['\\', '/']);
+        let parent = if trimmed.ends_with(':') { format!("{}\\", trimmed) } else { trimmed.to_string() };
+
+        let limit = config::MAX_FOLDER_CHILDREN_SCAN.to_string();
+        let filter = format!("parent:\"{}\"", parent);
+        let mut args = vec![
+            "-n", limit.as_str(), "-tsv", "-name", "-size", "-dm", "-attributes",
+            // FILETIME 数值，不受系统区域格式影响
+            "-date-format", "2",
+            filter.as_str(),
+        ];
+        if !config::ES_INSTANCE.is_empty() {
+            args.splice(0..0, ["-instance", config::ES_INSTANCE]);
+        }
+
+        let stdout = run_es_silent(es_path, &args).ok()?;
+        Some(parse_children(&stdout))
+    }
+
     #[allow(dead_code)]
     pub fn search_content(&self, _query: &str) -> Vec<crate::content_search::ContentMatch> {
         Vec::new()
@@ -351,17 +377,52 @@ fn process_tsv_line(line: &str, results: &mut Vec<SearchEntry>) {
     }
 }
 
-use std::path::Path;
-use std::os::windows::process::CommandExt;
+/// 解析 `list_children` 的 TSV 输出；按表头定位列，列顺序随 es.exe 版本可能不同
+fn parse_children(stdout: &str) -> Vec<crate::preview::FolderChild> {
+    let mut lines = stdout.lines();
+    let Some(header) = lines.next() else { return Vec::new() };
+    let columns: Vec<&str> = header.split('\t').map(|c| c.trim().trim_matches('"')).collect();
+    let column = |name: &str| columns.iter().position(|c| c.eq_ignore_ascii_case(name));
+    let (Some(name_col), size_col, modified_col, attr_col) =
+        (column("Name").or(column("Filename")), column("Size"), column("Date Modified"), column("Attributes"))
+    else {
+        return Vec::new();
+    };
 
-fn run_es_silent(es_path: &Path, args: &[&str]) -> Result<String, String> {
-    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    lines
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split('\t').map(|p| p.trim().trim_matches('"')).collect();
+            let field = |col: Option<usize>| col.and_then(|i| parts.get(i)).copied().unwrap_or("");
+            let name = field(Some(name_col));
+            if name.is_empty() {
+                return None;
+            }
+            // FILETIME：自 1601 年起的 100 纳秒数
+            let modified = field(modified_col)
+                .parse::<u64>()
+                .ok()
+                .and_then(|ft| (ft / 10_000_000).checked_sub(11_644_473_600))
+                .filter(|&secs| secs > 0);
+            Some(crate::preview::FolderChild {
+                name: name.to_string(),
+                is_dir: field(attr_col).contains('D'),
+                size: field(size_col).replace(',', "").parse().unwrap_or(0),
+                modified,
+            })
+        })
+        .collect()
+}
 
-    let output = Command::new(es_path)
-        .args(args)
-        .creation_flags(CREATE_NO_WINDOW)
-        .output()
-        .map_err(|e| format!("执行 es.exe 失败: {}", e))?;
+fn run_es_silent(es_path: &Path, args: &[&str]) -> Result<String, String> {
+    let mut cmd = Command::new(es_path);
+    cmd.args(args);
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = cmd.output().map_err(|e| format!("执行 es.exe 失败: {}", e))?;
 
     // 智能检测编码：先尝试 UTF-8，如果不包含错误则使用；否则尝试 GBK
     let stdout_bytes = &output.stdout;
@@ -403,7 +464,6 @@ fn ensure_everything_running(es_path: &Path, exe_path: &PathBuf, instance: &str)
     // Windows 下彻底隐藏启动窗口
     #[cfg(target_os = "windows")]
     {
-        use std::os::windows::process::CommandExt;
         const CREATE_NO_WINDOW: u32 = 0x08000000;
         cmd.creation_flags(CREATE_NO_WINDOW);
     }
@@ -1,11 +1,9 @@
 use std::sync::Arc;
-use tokio::net::windows::named_pipe::ServerOptions;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use anyhow::{Result, Context};
 use tracing::{info, error};
 use std::time::Duration;
 
-use crate::ipc::PIPE_NAME;
 use crate::ntfs_search::LocalNtfsSearcher;
 use crate::types::{SearchRequest, SearchResponse, SearchResultItem};
 
@@ -98,6 +96,18 @@ fn service_main_wrapper(args: Vec<std::ffi::OsString>) {
     }
 }
 
+/// `service run`：由服务管理器启动时以服务方式运行，在控制台中直接运行时于前台提供检索
+pub fn run() -> Result<()> {
+    #[cfg(windows)]
+    {
+        match main_service() {
+            Ok(()) => return Ok(()),
+            Err(e) => info!("未由服务管理器启动（{:#}），改为前台运行", e),
+        }
+    }
+    tokio::runtime::Runtime::new()?.block_on(run_service_logic())
+}
+
 async fn run_service_logic() -> Result<()> {
     info!("正在启动 StarSearch 服务逻辑...");
     
@@ -111,10 +121,11 @@ async fn run_service_logic() -> Result<()> {
         }
     });
     
+    #[cfg(windows)]
     loop {
-        let server = ServerOptions::new()
+        let server = tokio::net::windows::named_pipe::ServerOptions::new()
             .first_pipe_instance(true)
-            .create(PIPE_NAME)
+            .create(crate::ipc::PIPE_NAME)
             .context("创建命名管道失败")?;
 
         server.connect().await.context("等待客户端连接失败")?;
@@ -126,9 +137,37 @@ async fn run_service_logic() -> Result<()> {
             }
         });
     }
+
+    #[cfg(not(windows))]
+    {
+        let socket = crate::ipc::socket_path();
+        let listener = bind_socket(&socket).await?;
+        info!("服务已在 {} 上监听", socket.display());
+
+        loop {
+            let (stream, _) = listener.accept().await.context("等待客户端连接失败")?;
+            let searcher_task = searcher.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_client(stream, searcher_task).await {
+                    error!("处理客户端请求失败: {}", e);
+                }
+            });
+        }
+    }
+}
+
+/// 绑定服务套接字；已有服务在监听时报错，只清理上次异常退出留下的套接字文件
+#[cfg(not(windows))]
+async fn bind_socket(socket: &std::path::Path) -> Result<tokio::net::UnixListener> {
+    if tokio::net::UnixStream::connect(socket).await.is_ok() {
+        anyhow::bail!("已有服务在 {} 上运行", socket.display());
+    }
+    // 连不上的套接字文件会导致绑定失败
+    let _ = std::fs::remove_file(socket);
+    tokio::net::UnixListener::bind(socket).with_context(|| format!("创建套接字 {} 失败", socket.display()))
 }
 
-async fn handle_client(mut server: tokio::net::windows::named_pipe::NamedPipeServer, searcher: Arc<LocalNtfsSearcher>) -> Result<()> {
+async fn handle_client<S: AsyncRead + AsyncWrite + Unpin>(mut server: S, searcher: Arc<LocalNtfsSearcher>) -> Result<()> {
     let mut buffer = vec![0u8; 4096];
     let n = server.read(&mut buffer).await?;
     
@@ -139,18 +178,13 @@ async fn handle_client(mut server: tokio::net::windows::named_pipe::NamedPipeSer
     let response = match serde_json::from_slice::<SearchRequest>(&buffer[..n]) {
         Ok(request) => {
             let start = std::time::Instant::now();
-            let results = searcher.search(&request.query, request.max_results).await;
+            let index_ready = searcher.is_ready().await;
+            let results = searcher.search_sorted(&request.query, request.max_results, request.sort, request.reverse).await;
             let elapsed = start.elapsed().as_millis() as u64;
             
             let result_items: Vec<SearchResultItem> = results.into_iter().map(|e| SearchResultItem {
-                name: e.name,
-                path: e.path,
-                extension: e.extension,
-                size: e.size,
-                modified: e.modified,
-                is_dir: e.is_dir,
-                drive: e.drive,
                 score: 1.0,
+                ..e
             }).collect();
 
             SearchResponse {
@@ -160,6 +194,7 @@ async fn handle_client(mut server: tokio::net::windows::named_pipe::NamedPipeSer
                 results: result_items,
                 total: 0, // 暂时填0，后续完善
                 error: None,
+                index_ready,
             }
         }
         Err(e) => SearchResponse {
@@ -169,6 +204,7 @@ async fn handle_client(mut server: tokio::net::windows::named_pipe::NamedPipeSer
             results: Vec::new(),
             total: 0,
             error: Some(format!("请求解析失败: {}", e)),
+            index_ready: searcher.is_ready().await,
         }
     };
 
@@ -182,11 +218,12 @@ async fn handle_client(mut server: tokio::net::windows::named_pipe::NamedPipeSer
     Ok(())
 }
 
+#[cfg(windows)]
 pub fn install_service() -> Result<()> {
     let exe_path = std::env::current_exe()?;
     
-    // 使用 sc.exe 安装，更加标准，并指定 --service 参数
-    let bin_path = format!("\"{}\" --service", exe_path.display());
+    // 使用 sc.exe 安装，更加标准，由服务管理器以 `service run` 子命令启动
+    let bin_path = format!("\"{}\" service run", exe_path.display());
     
     // 1. 删除旧服务 (如果存在)
     let _ = std::process::Command::new("sc").args(["delete", SERVICE_NAME]).output();
@@ -218,6 +255,12 @@ pub fn install_service() -> Result<()> {
     Ok(())
 }
 
+#[cfg(not(windows))]
+pub fn install_service() -> Result<()> {
+    anyhow::bail!("系统服务安装仅支持 Windows，可用 `starsearch service run` 在前台运行")
+}
+
+#[cfg(windows)]
 pub fn uninstall_service() -> Result<()> {
     let _ = std::process::Command::new("sc").args(["stop", SERVICE_NAME]).output();
     let output = std::process::Command::new("sc").args(["delete", SERVICE_NAME]).output()?;
@@ -229,3 +272,31 @@ pub fn uninstall_service() -> Result<()> {
         Err(anyhow::anyhow!("服务卸载失败: {}", err))
     }
 }
+
+#[cfg(not(windows))]
+pub fn uninstall_service() -> Result<()> {
+    anyhow::bail!("系统服务安装仅支持 Windows")
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn live_socket_is_not_taken_over() {
+        let socket = std::env::temp_dir().join(format!("starsearch-service-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+
+        let first = bind_socket(&socket).await.unwrap();
+        assert!(bind_socket(&socket).await.is_err());
+        // 第一个服务的套接字仍可连接
+        assert!(tokio::net::UnixStream::connect(&socket).await.is_ok());
+
+        // 进程退出后留下的套接字文件可以重新绑定
+        drop(first);
+        assert!(socket.exists());
+        let second = bind_socket(&socket).await.unwrap();
+        drop(second);
+        std::fs::remove_file(&socket).ok();
+    }
+}
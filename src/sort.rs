@@ -0,0 +1,129 @@
+use std::cmp::Ordering;
+use std::path::Path;
+
+use crate::content_search::ContentMatch;
+use crate::types::{FileEntry, SortKey};
+
+/// 排序键的取值：文本按不区分大小写比较，数值与匹配度按大小比较
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum SortValue {
+    Text(String),
+    Number(u64),
+    Score(f32),
+}
+
+impl SortKey {
+    /// 大小、修改时间、匹配度默认从大到小，名称类从小到大
+    pub fn descending(self) -> bool {
+        matches!(self, SortKey::Size | SortKey::Modified | SortKey::Score)
+    }
+}
+
+/// 取排序后的前 `k` 项：先 `select_nth_unstable` 选出前 k 个（O(n)），只对这 k 个排序，
+/// 百万候选取 10 条时无需整体排序。相同键保持原有先后顺序
+pub fn top_k<T>(items: Vec<T>, k: usize, key: SortKey, reverse: bool, value: impl Fn(&T) -> SortValue) -> Vec<T> {
+    let descending = key.descending() != reverse;
+    let compare = |a: &(SortValue, usize, T), b: &(SortValue, usize, T)| {
+        let order = a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal);
+        let order = if descending { order.reverse() } else { order };
+        order.then(a.1.cmp(&b.1))
+    };
+
+    if k == 0 {
+        return Vec::new();
+    }
+    let mut keyed: Vec<(SortValue, usize, T)> = items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| (value(&item), i, item))
+        .collect();
+    if keyed.len() > k {
+        keyed.select_nth_unstable_by(k - 1, compare);
+        keyed.truncate(k);
+    }
+    keyed.sort_unstable_by(compare);
+    keyed.into_iter().map(|(_, _, item)| item).collect()
+}
+
+/// 文件条目的排序键
+pub fn entry_value(entry: &FileEntry, key: SortKey) -> SortValue {
+    match key {
+        SortKey::Name => SortValue::Text(entry.name.to_lowercase()),
+        SortKey::Path => SortValue::Text(entry.path.to_lowercase()),
+        SortKey::Ext => SortValue::Text(entry.extension.to_lowercase()),
+        SortKey::Size => SortValue::Number(entry.size),
+        SortKey::Modified => SortValue::Number(entry.modified),
+        SortKey::Score => SortValue::Score(entry.score),
+    }
+}
+
+/// 内容匹配的排序键；大小与修改时间需读取文件元数据
+pub fn match_value(m: &ContentMatch, key: SortKey) -> SortValue {
+    path_value(&m.full_path, m.score, key)
+}
+
+/// 按路径取排序键，供只有路径和匹配度的结果使用
+pub fn path_value(path: &str, score: f32, key: SortKey) -> SortValue {
+    let path_ref = Path::new(path);
+    let text = |s: Option<&std::ffi::OsStr>| SortValue::Text(s.map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default());
+    match key {
+        SortKey::Name => text(path_ref.file_name()),
+        SortKey::Path => SortValue::Text(path.to_lowercase()),
+        SortKey::Ext => text(path_ref.extension()),
+        SortKey::Size => SortValue::Number(std::fs::metadata(path_ref).map(|m| m.len()).unwrap_or(0)),
+        SortKey::Modified => SortValue::Number(
+            std::fs::metadata(path_ref)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        ),
+        SortKey::Score => SortValue::Score(score),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(items: &[(&'static str, u64)], k: usize, key: SortKey, reverse: bool) -> Vec<&'static str> {
+        let value = |item: &(&str, u64)| match key {
+            SortKey::Size => SortValue::Number(item.1),
+            _ => SortValue::Text(item.0.to_lowercase()),
+        };
+        top_k(items.to_vec(), k, key, reverse, value).into_iter().map(|(name, _)| name).collect()
+    }
+
+    const ITEMS: &[(&str, u64)] = &[("b", 5), ("A", 1), ("c", 9), ("d", 5), ("e", 3)];
+
+    #[test]
+    fn size_is_descending_by_default() {
+        assert_eq!(sizes(ITEMS, 3, SortKey::Size, false), ["c", "b", "d"]);
+        assert_eq!(sizes(ITEMS, 2, SortKey::Size, true), ["A", "e"]);
+    }
+
+    #[test]
+    fn name_is_ascending_and_case_insensitive() {
+        assert_eq!(sizes(ITEMS, 2, SortKey::Name, false), ["A", "b"]);
+        assert_eq!(sizes(ITEMS, 2, SortKey::Name, true), ["e", "d"]);
+    }
+
+    #[test]
+    fn ties_keep_original_order() {
+        assert_eq!(sizes(ITEMS, 5, SortKey::Size, false), ["c", "b", "d", "e", "A"]);
+    }
+
+    #[test]
+    fn k_bounds() {
+        assert!(sizes(ITEMS, 0, SortKey::Size, false).is_empty());
+        assert_eq!(sizes(ITEMS, 100, SortKey::Size, false).len(), ITEMS.len());
+    }
+
+    #[test]
+    fn path_value_reads_extension_and_name() {
+        assert_eq!(path_value("/x/Foo.RS", 0.0, SortKey::Ext), SortValue::Text("rs".into()));
+        assert_eq!(path_value("/x/Foo.RS", 0.0, SortKey::Name), SortValue::Text("foo.rs".into()));
+        assert_eq!(path_value("/nonexistent/zz", 0.0, SortKey::Size), SortValue::Number(0));
+    }
+}
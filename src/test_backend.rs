@@ -1,8 +1,20 @@
 #![allow(dead_code)]
+mod archive;
 mod searcher;
 mod config;
 mod content_search;
+mod encoding;
+mod extract;
+mod fulltext;
+mod hash;
 mod ntfs_search;
+mod pe;
+mod preview;
+mod query;
+mod ranking;
+mod replace;
+mod sort;
+mod thumbnail;
 mod types;
 
 use searcher::SearchBackend;
@@ -0,0 +1,270 @@
+use anyhow::{bail, Result};
+use image::DynamicImage;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{self, MAX_THUMBNAIL_CACHE_BYTES, MAX_THUMBNAIL_SOURCE_BYTES};
+use crate::hash::StableHasher;
+
+/// 生成缩略图的图片扩展名
+pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "bmp"];
+
+/// 结果行缩略图边长
+pub const ROW_THUMBNAIL_SIZE: u32 = 48;
+/// 预览栏缩略图边长
+pub const PREVIEW_THUMBNAIL_SIZE: u32 = 512;
+
+/// 缩略图生成线程数，避免与搜索争抢 CPU
+const THUMBNAIL_THREADS: usize = 2;
+
+/// 每新写入这么多张缩略图检查一次缓存大小
+const PRUNE_EVERY: usize = 200;
+
+/// 本进程写入的缩略图数，用于定期清理缓存
+static CACHE_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// 已按 EXIF 方向摆正的缩略图（RGBA8）
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+    /// 原图尺寸（已按 EXIF 方向交换宽高）
+    pub source_width: u32,
+    pub source_height: u32,
+}
+
+/// 取缩略图：先查数据目录下的缓存（按路径 + 修改时间 + 大小 + 边长），未命中再解码原图生成并写入缓存
+pub fn thumbnail(path: &Path, max_side: u32) -> Result<Thumbnail> {
+    let metadata = std::fs::metadata(path)?;
+    if metadata.len() > MAX_THUMBNAIL_SOURCE_BYTES {
+        bail!("图片过大（{} 字节），不生成缩略图", metadata.len());
+    }
+
+    let orientation = exif_orientation(path);
+    let (mut source_width, mut source_height) = image::image_dimensions(path)?;
+    if orientation >= 5 {
+        std::mem::swap(&mut source_width, &mut source_height);
+    }
+
+    let cache_path = cache_path(path, &metadata, max_side);
+    let cached = image::open(&cache_path).ok();
+    let thumb = match cached {
+        Some(img) => {
+            // 命中时刷新修改时间，清理按修改时间淘汰最久未用的
+            if let Ok(file) = std::fs::File::options().write(true).open(&cache_path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+            img
+        }
+        None => {
+            let img = apply_orientation(image::open(path)?, orientation);
+            // 小图不放大
+            let img = if img.width() > max_side || img.height() > max_side {
+                img.thumbnail(max_side, max_side)
+            } else {
+                img
+            };
+            if let Some(parent) = cache_path.parent() {
+                std::fs::create_dir_all(parent).ok();
+            }
+            if let Err(e) = img.save_with_format(&cache_path, image::ImageFormat::Png) {
+                tracing::warn!("写入缩略图缓存失败 {}: {}", cache_path.display(), e);
+            } else if (CACHE_WRITES.fetch_add(1, Ordering::Relaxed) + 1).is_multiple_of(PRUNE_EVERY) {
+                prune_cache(&config::thumbnail_cache_dir(), MAX_THUMBNAIL_CACHE_BYTES);
+            }
+            img
+        }
+    };
+
+    let rgba = thumb.to_rgba8();
+    Ok(Thumbnail {
+        width: rgba.width(),
+        height: rgba.height(),
+        rgba: rgba.into_raw(),
+        source_width,
+        source_height,
+    })
+}
+
+fn cache_path(path: &Path, metadata: &std::fs::Metadata, max_side: u32) -> PathBuf {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let key = StableHasher::new()
+        .write_str(&path.to_string_lossy())
+        .write(&modified.to_le_bytes())
+        .write_u64(metadata.len())
+        .write_u64(max_side as u64)
+        .finish();
+    config::thumbnail_cache_dir().join(format!("{:016x}.png", key))
+}
+
+/// 缓存超过 `max_bytes` 时按修改时间从旧到新删除，直到降到上限的 3/4，避免每次写入都要清理
+fn prune_cache(dir: &Path, max_bytes: u64) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else { return };
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = read_dir
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "png"))
+        .filter_map(|e| {
+            let metadata = e.metadata().ok().filter(|m| m.is_file())?;
+            Some((metadata.modified().unwrap_or(UNIX_EPOCH), metadata.len(), e.path()))
+        })
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    if total <= max_bytes {
+        return;
+    }
+
+    files.sort();
+    let target = max_bytes / 4 * 3;
+    for (_, size, path) in files {
+        if total <= target {
+            break;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => total -= size,
+            Err(e) => tracing::debug!("删除缩略图缓存失败 {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// EXIF 方向（1-8），读不到时按 1（正常）处理
+fn exif_orientation(path: &Path) -> u32 {
+    let Ok(file) = std::fs::File::open(path) else { return 1 };
+    let mut reader = std::io::BufReader::new(file);
+    exif::Reader::new()
+        .read_from_container(&mut reader)
+        .ok()
+        .and_then(|data| {
+            data.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        })
+        .filter(|o| (1..=8).contains(o))
+        .unwrap_or(1)
+}
+
+fn apply_orientation(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+/// 缩略图生成结果：（路径，边长，缩略图或错误信息）
+pub type ThumbnailResult = (PathBuf, u32, std::result::Result<Thumbnail, String>);
+
+/// 后台缩略图线程池：GUI 每帧提交可见结果，生成完成后通过 `poll` 取回，不阻塞界面
+pub struct ThumbnailPool {
+    pool: rayon::ThreadPool,
+    sender: Sender<ThumbnailResult>,
+    receiver: Receiver<ThumbnailResult>,
+    pending: Mutex<HashSet<(PathBuf, u32)>>,
+}
+
+impl ThumbnailPool {
+    pub fn new() -> Self {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(THUMBNAIL_THREADS)
+            .thread_name(|i| format!("thumbnail-{}", i))
+            .build()
+            .expect("无法创建缩略图线程池");
+        let (sender, receiver) = channel();
+        // 启动时在后台检查一次缓存大小
+        pool.spawn(|| prune_cache(&config::thumbnail_cache_dir(), MAX_THUMBNAIL_CACHE_BYTES));
+        Self { pool, sender, receiver, pending: Mutex::new(HashSet::new()) }
+    }
+
+    /// 提交生成请求；同一图片同一尺寸正在生成时忽略
+    pub fn request(&self, path: &Path, max_side: u32) {
+        let key = (path.to_path_buf(), max_side);
+        if !self.pending.lock().unwrap().insert(key.clone()) {
+            return;
+        }
+        let sender = self.sender.clone();
+        self.pool.spawn(move || {
+            let result = thumbnail(&key.0, max_side).map_err(|e| e.to_string());
+            let _ = sender.send((key.0, max_side, result));
+        });
+    }
+
+    /// 取回已完成的缩略图；取回后才解除去重，避免结果入库前被重复提交
+    pub fn poll(&self) -> Vec<ThumbnailResult> {
+        let done: Vec<ThumbnailResult> = self.receiver.try_iter().collect();
+        if !done.is_empty() {
+            let mut pending = self.pending.lock().unwrap();
+            for (path, size, _) in &done {
+                pending.remove(&(path.clone(), *size));
+            }
+        }
+        done
+    }
+}
+
+impl Default for ThumbnailPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_removes_the_least_recently_used_first() {
+        let dir = std::env::temp_dir().join(format!("starsearch-thumbnail-prune-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        for (i, name) in ["old.png", "mid.png", "new.png"].iter().enumerate() {
+            let path = dir.join(name);
+            std::fs::write(&path, vec![0u8; 100]).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(300 - i as u64 * 100)).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), vec![0u8; 1000]).unwrap();
+
+        // 未超上限不删
+        prune_cache(&dir, 300);
+        assert!(dir.join("old.png").exists());
+
+        // 超出后删到上限的 3/4：250 -> 删 old、mid
+        prune_cache(&dir, 250);
+        assert!(!dir.join("old.png").exists());
+        assert!(!dir.join("mid.png").exists());
+        assert!(dir.join("new.png").exists());
+        assert!(dir.join("notes.txt").exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn cache_key_is_stable_and_depends_on_size() {
+        let path = std::env::temp_dir().join(format!("starsearch-thumbnail-key-{}.png", std::process::id()));
+        std::fs::write(&path, b"x").unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        let key = cache_path(&path, &metadata, ROW_THUMBNAIL_SIZE);
+        assert_eq!(key, cache_path(&path, &metadata, ROW_THUMBNAIL_SIZE));
+        assert_ne!(key, cache_path(&path, &metadata, PREVIEW_THUMBNAIL_SIZE));
+        std::fs::remove_file(&path).ok();
+    }
+}
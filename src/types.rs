@@ -1,6 +1,17 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 条目来源：正常文件、从 MFT 未使用记录中恢复的已删除文件、NTFS 备用数据流，或压缩包成员
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    #[default]
+    Normal,
+    Deleted,
+    Stream,
+    ArchiveMember,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FileEntry {
     pub name: String,
     pub path: String,
@@ -10,6 +21,41 @@ pub struct FileEntry {
     pub is_dir: bool,
     pub drive: char,
     pub score: f32,
+    #[serde(default)]
+    pub kind: EntryKind,
+    /// 已删除文件的数据簇是否仍未被占用（`None` 表示不适用或无法判断）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recoverable: Option<bool>,
+    /// 同一 MFT 记录的其他硬链接路径
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,
+    /// 组合查询（`content:`）时文件内匹配的行
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub matches: Vec<ContentLine>,
+}
+
+/// 文件条目上附带的内容匹配行
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContentLine {
+    pub line_number: u64,
+    pub line_content: String,
+    /// 文档内位置（段落/工作表行/幻灯片/页）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locator: Option<String>,
+}
+
+/// 结果排序字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// 后端自身的相关度顺序
+    #[default]
+    Score,
+    Name,
+    Path,
+    Size,
+    Modified,
+    Ext,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +65,11 @@ pub struct SearchRequest {
     pub max_results: usize,
     pub scope: Option<String>,
     pub extensions: Option<Vec<String>>,
+    #[serde(default)]
+    pub sort: SortKey,
+    /// 反转排序方向
+    #[serde(default)]
+    pub reverse: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +80,13 @@ pub struct SearchResponse {
     pub elapsed_ms: u64,
     pub total_count: usize,
     pub error: Option<String>,
+    /// 服务端索引是否已加载完成；旧版服务不返回此字段，按已就绪处理
+    #[serde(default = "default_index_ready")]
+    pub index_ready: bool,
+}
+
+fn default_index_ready() -> bool {
+    true
 }
 
 pub type SearchResultItem = FileEntry;